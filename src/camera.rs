@@ -1,13 +1,14 @@
 use bevy::prelude::*;
 use log::error;
 
-use crate::{globals::{WINDOW_SIZE, SPRITE_SIZE, MAP_SIZE}, ui::inspector::INSPECTOR_PANEL_SIZE};
+use crate::{globals::{WINDOW_SIZE, SPRITE_SIZE, MAP_SIZE}, ui::inspector::INSPECTOR_PANEL_SIZE, world::generation::start::StartArea};
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_system_to_stage(StartupStage::PreStartup, setup_camera)
+            .add_startup_system_to_stage(StartupStage::PostStartup, focus_start_area)
             .add_system_to_stage(CoreStage::PreUpdate, move_camera);
     }
 }
@@ -25,6 +26,18 @@ fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default()).insert(MainCamera);
 }
 
+// NOTE: Centers the camera on the starting cave, the
+//       position is restricted on the first camera update.
+fn focus_start_area(
+    mut query: Query<&mut Transform, With<MainCamera>>,
+    start: Res<StartArea>,
+) {
+    for mut transform in &mut query {
+        transform.translation.x = start.center.0 as f32 * SPRITE_SIZE;
+        transform.translation.y = start.center.1 as f32 * SPRITE_SIZE;
+    }
+}
+
 // NOTE: Contols and restricts the camera position.
 fn move_camera(
     mut query: Query<&mut Transform, With<MainCamera>>,
@@ -33,4 +33,11 @@ pub const SPRITE_ORDER_USER: f32 = 900.0;
 // ##### GAME #####
 
 // NOTE: Default size for the world in tiles. 
-pub const MAP_SIZE: (usize, usize) = (100, 100);
+pub const MAP_SIZE: (usize, usize) = (1000, 1000);
+
+// NOTE: Number of levels in the world, the first one is the surface.
+pub const MAP_DEPTH: usize = 3;
+
+// NOTE: Default seed of the world, every random number stream of the
+//       game is derived from it. Can be overridden with `--seed <n>`.
+pub const WORLD_SEED: u64 = 13;
@@ -4,6 +4,15 @@ pub mod core;
 pub mod worker;
 pub mod combat;
 pub mod stats;
+pub mod needs;
+pub mod skills;
+pub mod archetype;
+pub mod spell;
+pub mod hostile;
+pub mod status;
+pub mod initiative;
+pub mod faction;
+pub mod ai;
 
 #[allow(unused_imports)]
 pub mod prelude {
@@ -11,6 +20,14 @@ pub mod prelude {
     pub use super::core::prelude::*;
     pub use super::stats::prelude::*;
     pub use super::combat::prelude::*;
+    pub use super::needs::prelude::*;
+    pub use super::skills::prelude::*;
+    pub use super::archetype::prelude::*;
+    pub use super::spell::prelude::*;
+    pub use super::status::prelude::*;
+    pub use super::initiative::prelude::*;
+    pub use super::faction::prelude::*;
+    pub use super::ai::prelude::*;
 }
 
 use bevy::prelude::*;
@@ -21,6 +38,13 @@ impl Plugin for PawnPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(core::CorePlugin)
             .add_plugin(worker::WorkerPlugin)
-            .add_plugin(combat::CombatPlugin);
+            .add_plugin(combat::CombatPlugin)
+            .add_plugin(needs::NeedsPlugin)
+            .add_plugin(archetype::ArchetypePlugin)
+            .add_plugin(spell::SpellPlugin)
+            .add_plugin(status::StatusPlugin)
+            .add_plugin(initiative::InitiativePlugin)
+            .add_plugin(faction::FactionPlugin)
+            .add_plugin(ai::AiPlugin);
     }
 }
\ No newline at end of file
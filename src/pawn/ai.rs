@@ -0,0 +1,507 @@
+use std::{collections::{HashMap, VecDeque}, fs};
+use bevy::{prelude::*, asset::FileAssetIo};
+use rand::Rng;
+use serde::Deserialize;
+use log::{info, warn, error};
+
+use crate::{world::{self, room::prelude::*}, player::command, util::prelude::*, turn_system};
+use super::{prelude::*, core, worker, needs, hostile, faction};
+
+pub mod prelude {
+    pub use super::{
+        Brain,
+        RallyPoint,
+        GuardPost,
+    };
+}
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RallyPoint>()
+            .add_startup_system_to_stage(StartupStage::PreStartup, setup_brain_registry)
+            .add_startup_system_to_stage(StartupStage::Startup, validate_archetype_brains)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                think.after(turn_system::turn_system).after(command::ApplyCommands)
+            );
+    }
+}
+
+// NOTE: Path to the folder which holds brain definitions, relative
+//       to the base path of the game which holds the assets folder.
+//       Every `.ron` file in the folder is loaded as a single brain.
+const BRAIN_FOLDER: &str = "assets/brains";
+
+// NOTE: Extension of brain definition files.
+const BRAIN_EXTENSION: &str = "ron";
+
+// NOTE: Maximum distance in tiles at which a threat makes a pawn retreat.
+pub const RETREAT_RANGE: u32 = 6;
+
+// NOTE: Maximum distance in tiles from its post at which a guard engages threats.
+pub const GUARD_RADIUS: u32 = 5;
+
+// NOTE: Every condition a rule of a brain can require.
+//       - Idle          -> Pawn has no tasks.
+//       - Threatened    -> A pawn it's hostile to is in `AGGRO_RANGE`.
+//       - Safe          -> No pawn it's hostile to is in `AGGRO_RANGE`.
+//       - Endangered    -> A pawn it's hostile to is in `RETREAT_RANGE`.
+//       - Provoked      -> Pawn was attacked by a faction it's neutral to.
+//       - Hungry        -> Hunger is below its treshold.
+//       - Tired         -> Rest is below its treshold.
+//       - Hurt          -> Health is below the `HEAL_TRESHOLD`.
+//       - CanFight      -> Pawn has a melee or a ranged attack.
+//       - WorkAvailable -> Pawn is a worker with accessible works.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Condition {
+    Idle,
+    Threatened,
+    Safe,
+    Endangered,
+    Provoked,
+    Hungry,
+    Tired,
+    Hurt,
+    CanFight,
+    WorkAvailable,
+}
+
+// NOTE: Every action a rule of a brain can pick, actions turn into tasks.
+//       - Work    -> Takes the nearest global work.
+//       - Attack  -> Attacks the closest threat.
+//       - Heal    -> Casts a heal on itself.
+//       - Flee    -> Runs away from the closest threat.
+//       - Retreat -> Drops its work and goes to the rally point or a safe room,
+//                    runs away from the closest threat if there is neither.
+//       - Guard   -> Attacks the threats near its post, otherwise returns to it.
+//       - Wander  -> Sometimes walks to a random tile nearby.
+//       - Eat     -> Goes to eat at a stockpile.
+//       - Sleep   -> Goes to sleep at a dormitory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Action {
+    Work,
+    Attack,
+    Heal,
+    Flee,
+    Retreat,
+    Guard,
+    Wander,
+    Eat,
+    Sleep,
+}
+
+// NOTE: A single rule of a brain, the action is considered when every
+//       condition holds. Rules that interrupt are considered while the
+//       pawn is busy, their action replaces the tasks of the pawn.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub action: Action,
+    pub score: f32,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    #[serde(default)]
+    pub interrupt: bool,
+}
+
+// NOTE: Definition of a brain, loaded from the assets folder.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BrainDefinition {
+    pub name: String,
+    pub rules: Vec<Rule>,
+}
+
+// NOTE: Component that holds the name of the brain which drives the
+//       pawn, pawns without a brain only act on the orders they receive.
+#[derive(Component, Debug, Clone)]
+pub struct Brain(pub String);
+
+// NOTE: Resource which holds every loaded brain by their names.
+#[derive(Resource, Default)]
+pub struct BrainRegistry {
+    pub brains: HashMap<String, BrainDefinition>,
+}
+
+impl BrainRegistry {
+    pub fn get(&self, name: &str) -> Option<&BrainDefinition> {
+        return self.brains.get(name);
+    }
+}
+
+// NOTE: Resource that holds the rally point the player placed, pawns
+//       that retreat gather around it while it's out of danger.
+#[derive(Resource, Debug, Default)]
+pub struct RallyPoint(pub Option<Position>);
+
+// NOTE: Component that holds the position a fighter holds, it's set
+//       when the pawn is moved by the player. Pawns without a post
+//       hold the position they are standing on.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GuardPost(pub Position);
+
+// NOTE: Everything a pawn knows about itself and its surroundings
+//       when it's thinking, conditions are evaluated against these.
+struct Senses {
+    idle: bool,
+    threat: Option<(Entity, Position)>,
+    endangered: bool,
+    provoked: bool,
+    hungry: bool,
+    tired: bool,
+    hurt: bool,
+    can_fight: bool,
+    work_available: bool,
+}
+
+impl Condition {
+    fn holds(&self, senses: &Senses) -> bool {
+        return match self {
+            Condition::Idle => senses.idle,
+            Condition::Threatened => senses.threat.is_some(),
+            Condition::Safe => senses.threat.is_none(),
+            Condition::Endangered => senses.endangered,
+            Condition::Provoked => senses.provoked,
+            Condition::Hungry => senses.hungry,
+            Condition::Tired => senses.tired,
+            Condition::Hurt => senses.hurt,
+            Condition::CanFight => senses.can_fight,
+            Condition::WorkAvailable => senses.work_available,
+        };
+    }
+}
+
+// NOTE: Loads every brain definition in the brain folder,
+//       and creates the `BrainRegistry` resource.
+fn setup_brain_registry(mut commands: Commands) {
+    let path = FileAssetIo::get_base_path().join(BRAIN_FOLDER);
+
+    let entries = match fs::read_dir(&path) {
+        Ok(e) => e,
+        Err(e) => {
+            error!("Failed to read the brain folder `{}`: {}.", path.display(), e);
+            panic!();
+        }
+    };
+
+    let mut registry = BrainRegistry::default();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.extension().map_or(true, |e| e != BRAIN_EXTENSION) {
+            continue;
+        }
+
+        let source = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to read the brain file `{}`: {}.", path.display(), e);
+                panic!();
+            }
+        };
+
+        let brain: BrainDefinition = match ron::from_str(&source) {
+            Ok(b) => b,
+            Err(e) => {
+                error!("Failed to parse the brain file `{}`: {}.", path.display(), e);
+                panic!();
+            }
+        };
+
+        info!("Loaded brain `{}`.", brain.name);
+
+        registry.brains.insert(brain.name.clone(), brain);
+    }
+
+    commands.insert_resource(registry);
+}
+
+// NOTE: Checks that every brain an archetype uses, including the brains
+//       it uses once it's hired or tamed, is loaded.
+fn validate_archetype_brains(archetypes: Res<ArchetypeRegistry>, brains: Res<BrainRegistry>) {
+    for archetype in archetypes.archetypes.values() {
+        let names = [
+            archetype.brain.as_ref(),
+            archetype.hire.as_ref().and_then(|h| h.brain.as_ref()),
+            archetype.tame.as_ref().and_then(|h| h.brain.as_ref()),
+        ];
+
+        for name in names.into_iter().flatten() {
+            if brains.get(name).is_none() {
+                error!("Failed to find the brain `{}` of the archetype `{}`.", name, archetype.name);
+                panic!();
+            }
+        }
+    }
+}
+
+// NOTE: Decides what every pawn with a brain does at the end of every turn,
+//       in the order of the entities. The rules whose conditions hold are
+//       tried from the highest score to the lowest, until one of their
+//       actions results in tasks. Busy pawns only consider the rules that
+//       interrupt, and release their works when they're interrupted.
+fn think(
+    mut query: Query<(
+        Entity,
+        &Position,
+        &Alignment,
+        &Brain,
+        &Health,
+        &mut TaskQueue,
+        Option<&Abilities>,
+        Option<&SpellCooldowns>,
+        Option<&Provoked>,
+        Option<&Needs>,
+        Option<&GuardPost>,
+        Option<&mut worker::Worker>,
+    ), With<Pawn>>,
+    pawns: Query<(Entity, &Position, &Alignment), With<Pawn>>,
+    mut event_reader: EventReader<turn_system::TurnOverEvent>,
+    mut gw_validator: ResMut<worker::GlobalWorkValidator>,
+    mut rng: ResMut<GameRng>,
+    registry: Res<BrainRegistry>,
+    rally_point: Res<RallyPoint>,
+    factions: Res<Factions>,
+    rooms: Res<Rooms>,
+    world: Res<world::World>,
+) {
+    let mut over = false;
+    for _ in event_reader.iter() {
+        over = true;
+    }
+
+    if !over {
+        return;
+    }
+
+    let rng = rng.stream(RngStream::AI);
+
+    let others: Vec<(Entity, Position, Alignment)> = pawns.iter()
+        .map(|(e, p, a)| (e, *p, *a))
+        .collect();
+
+    let mut entities: Vec<Entity> = query.iter().map(|q| q.0).collect();
+    entities.sort();
+
+    for entity in entities {
+        let (
+            _, position, alignment, brain, health, mut tq,
+            abilities, cooldowns, provoked, needs, post, mut worker,
+        ) = match query.get_mut(entity) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        let brain = match registry.get(&brain.0) {
+            Some(b) => b,
+            None => {
+                warn!("Failed to find a brain with the name `{}`, pawn is skipped.", brain.0);
+                continue;
+            }
+        };
+
+        let threat = hostile::find_threat(position, *alignment, provoked, &others, &factions);
+
+        let senses = Senses {
+            idle: matches!(tq.active, Task::None) && tq.queue.is_empty(),
+            threat,
+            endangered: threat.map_or(false, |(_, p)| p.distance(position) <= RETREAT_RANGE.pow(2)),
+            provoked: provoked.is_some(),
+            hungry: needs.map_or(false, |n| n.hungry()),
+            tired: needs.map_or(false, |n| n.tired()),
+            hurt: (health.current as f32) < health.maximum as f32 * hostile::HEAL_TRESHOLD,
+            can_fight: abilities.map_or(false, |a| a.has(Ability::Melee) || a.has(Ability::Ranged)),
+            work_available: worker.as_ref().map_or(false, |w| !w.accessible.is_empty()),
+        };
+
+        let mut rules: Vec<&Rule> = brain.rules.iter()
+            .filter(|r| senses.idle || r.interrupt)
+            .filter(|r| r.conditions.iter().all(|c| c.holds(&senses)))
+            .collect();
+
+        rules.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        for rule in rules {
+            let tasks = match rule.action {
+                Action::Work => match worker.as_deref_mut() {
+                    Some(w) => worker::take_work(position, w, abilities, Some(alignment), &mut gw_validator, &world, &factions),
+                    None => None,
+                },
+                Action::Attack => match (senses.threat, abilities) {
+                    (Some((target, threat)), Some(a)) => Some(hostile::pick_attack_tasks(
+                        position, (target, &threat), a, cooldowns, Some(alignment), &world, &factions
+                    )),
+                    _ => None,
+                },
+                Action::Heal => {
+                    let can_heal = abilities.map_or(false, |a| a.has(Ability::Cast(Spell::Heal)))
+                        && cooldowns.map_or(true, |c| c.ready(Spell::Heal));
+
+                    if can_heal { Some(vec![Task::Cast((Spell::Heal, *position))]) } else { None }
+                },
+                Action::Flee => senses.threat
+                    .and_then(|(_, threat)| faction::find_flee_path(position, &threat, alignment, &world, &factions))
+                    .map(|mt| vec![Task::Move(mt)]),
+                Action::Retreat => senses.threat
+                    .and_then(|(_, threat)| find_retreat_path(
+                        position, &threat, alignment, &tq, rally_point.0, &rooms, &world, &factions
+                    ))
+                    .map(|mt| vec![Task::Move(mt)]),
+                Action::Guard => guard(
+                    position,
+                    post.map_or(*position, |p| p.0),
+                    senses.threat,
+                    abilities,
+                    cooldowns,
+                    alignment,
+                    &world,
+                    &factions,
+                ),
+                Action::Wander => if rng.gen_bool(faction::WANDER_CHANCE) {
+                    faction::find_wander_path(position, alignment, &world, &factions, rng).map(|mt| vec![Task::Move(mt)])
+                } else {
+                    None
+                },
+                Action::Eat => needs::tend_need(position, alignment, RoomKind::Stockpile, &tq, &rooms, &world, &factions),
+                Action::Sleep => needs::tend_need(position, alignment, RoomKind::Dormitory, &tq, &rooms, &world, &factions),
+            };
+
+            let tasks = match tasks {
+                Some(t) if !t.is_empty() => t,
+                _ => continue,
+            };
+
+            // NOTE: Release any work the pawn was tasked with, so other workers can take it.
+            if !senses.idle {
+                info!("Pawn is dropping its tasks to {:?}.", rule.action);
+
+                worker::release_task_queue(&mut tq, &mut gw_validator);
+            }
+
+            tq.queue.extend(tasks);
+
+            break;
+        }
+    }
+}
+
+// NOTE: Returns whether the tile is out of the threat's reach, and
+//       either around the rally point or in a dormitory.
+fn is_safe_spot(
+    target: &Position,
+    threat: &Position,
+    rally_point: Option<Position>,
+    rooms: &Rooms,
+) -> bool {
+    if target.distance(threat) <= RETREAT_RANGE.pow(2) {
+        return false;
+    }
+
+    if rally_point.map_or(false, |r| target.distance(&r) <= 2) {
+        return true;
+    }
+
+    return rooms.get(target) == Some(RoomKind::Dormitory);
+}
+
+// NOTE: Returns a path to the rally point if the threat isn't near it,
+//       otherwise to the closest dormitory tile out of the threat's
+//       reach. Pawns that have neither flee from the threat. Pawns
+//       that are already safe, or on their way, don't move.
+fn find_retreat_path(
+    position: &Position,
+    threat: &Position,
+    alignment: &Alignment,
+    task_queue: &TaskQueue,
+    rally_point: Option<Position>,
+    rooms: &Rooms,
+    world: &world::World,
+    factions: &Factions,
+) -> Option<MoveTask> {
+    let retreating = std::iter::once(&task_queue.active)
+        .chain(task_queue.queue.iter())
+        .any(|t| matches!(t, Task::Move(mt) if is_safe_spot(&mt.target, threat, rally_point, rooms)));
+
+    if retreating || is_safe_spot(position, threat, rally_point, rooms) {
+        return None;
+    }
+
+    if let Some(rally) = rally_point.filter(|r| r.distance(threat) > RETREAT_RANGE.pow(2)) {
+        let free = world.is_passable_tile(rally.into(), Some(alignment), factions) && world.get_entity(rally.into()).is_none();
+
+        // NOTE: Pawns gather around the rally point when it's taken.
+        let result = if free {
+            core::pawn_find_path(*position, rally, world, Some(alignment), factions).map(|(mut path, _)| {
+                path.remove(0);
+
+                MoveTask { path: VecDeque::from(path), target: rally }
+            })
+        } else {
+            worker::find_best_path_to_target(position, &rally, world, Some(alignment), factions)
+        };
+
+        if result.is_some() {
+            return result;
+        }
+    }
+
+    for target in rooms.sorted_by_distance(RoomKind::Dormitory, position) {
+        if target.distance(threat) <= RETREAT_RANGE.pow(2) || world.get_entity(target.into()).is_some() {
+            continue;
+        }
+
+        if let Some((mut path, _)) = core::pawn_find_path(*position, target, world, Some(alignment), factions) {
+            path.remove(0);
+
+            return Some(MoveTask { path: VecDeque::from(path), target });
+        }
+    }
+
+    return faction::find_flee_path(position, threat, alignment, world, factions);
+}
+
+// NOTE: Attacks the closest threat if it's within `GUARD_RADIUS` of the
+//       post, otherwise returns to the post. Guards don't chase threats
+//       that are farther from their post.
+fn guard(
+    position: &Position,
+    post: Position,
+    threat: Option<(Entity, Position)>,
+    abilities: Option<&Abilities>,
+    cooldowns: Option<&SpellCooldowns>,
+    alignment: &Alignment,
+    world: &world::World,
+    factions: &Factions,
+) -> Option<Vec<Task>> {
+    if let (Some((target, threat)), Some(abilities)) = (threat, abilities) {
+        if threat.distance(&post) <= GUARD_RADIUS.pow(2) {
+            let tasks = hostile::pick_attack_tasks(
+                position, (target, &threat), abilities, cooldowns, Some(alignment), world, factions
+            );
+
+            if !tasks.is_empty() {
+                return Some(tasks);
+            }
+        }
+    }
+
+    if *position == post {
+        return None;
+    }
+
+    // NOTE: Guards whose post is taken hold next to it.
+    if world.get_entity(post.into()).is_some() {
+        if position.distance(&post) <= 2 {
+            return None;
+        }
+
+        return worker::find_best_path_to_target(position, &post, world, Some(alignment), factions)
+            .map(|mt| vec![Task::Move(mt)]);
+    }
+
+    let (mut path, _) = core::pawn_find_path(*position, post, world, Some(alignment), factions)?;
+    path.remove(0);
+
+    return Some(vec![Task::Move(MoveTask { path: VecDeque::from(path), target: post })]);
+}
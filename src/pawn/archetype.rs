@@ -0,0 +1,252 @@
+use std::{collections::HashMap, fs};
+use bevy::{prelude::*, asset::FileAssetIo};
+use serde::Deserialize;
+use log::{info, error};
+
+use crate::{world::{self, tile::prelude::*}, tileset, util::prelude::*};
+use super::{prelude::*, core, worker};
+
+pub mod prelude {
+    pub use super::{
+        Ability,
+        Abilities,
+        Behaviour,
+        Archetype,
+        ArchetypeName,
+        ArchetypeRegistry,
+    };
+}
+
+pub struct ArchetypePlugin;
+
+impl Plugin for ArchetypePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, setup_archetype_registry);
+    }
+}
+
+// NOTE: Path to the folder which holds archetype definitions, relative
+//       to the base path of the game which holds the assets folder.
+//       Every `.ron` file in the folder is loaded as a single archetype.
+const ARCHETYPE_FOLDER: &str = "assets/archetypes";
+
+// NOTE: Extension of archetype definition files.
+const ARCHETYPE_EXTENSION: &str = "ron";
+
+// NOTE: Every ability a pawn can have, abilities determine
+//       which actions a pawn is capable of performing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Ability {
+    Melee,
+    Ranged,
+    Mine,
+    Cast(Spell),
+}
+
+// NOTE: Component that holds the abilities of a pawn.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Abilities(pub Vec<Ability>);
+
+impl Abilities {
+    pub fn has(&self, ability: Ability) -> bool {
+        return self.0.contains(&ability);
+    }
+
+    // NOTE: Returns every spell the pawn is able to cast.
+    pub fn spells(&self) -> Vec<Spell> {
+        return self.0.iter().filter_map(|a| match a {
+            Ability::Cast(spell) => Some(*spell),
+            _ => None,
+        }).collect();
+    }
+}
+
+// NOTE: Determines which kind of pawn it is, decisions are made by its brain.
+//       - Idle   -> Pawn doesn't take global works.
+//       - Worker -> Pawn is able to take global works such as mining.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Behaviour {
+    #[default]
+    Idle,
+    Worker,
+}
+
+// NOTE: Price of recruiting or summoning a pawn of an archetype, the
+//       gold that is paid to it every day as its wage, and the brain it
+//       uses once it joins the player instead of the archetype's brain.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hire {
+    pub cost: Vec<(ResourceMaterial, usize)>,
+    #[serde(default)]
+    pub wage: usize,
+    #[serde(default)]
+    pub brain: Option<String>,
+}
+
+// NOTE: Definition of a kind of pawn, loaded from the assets folder.
+//       Archetypes without a hire can't be recruited by the player, and
+//       neutral pawns of archetypes without a tame can't be tamed.
+//       Stats are rolled if they are not given, and color falls back
+//       to the color of the pawn's `Alignment`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Archetype {
+    pub name: String,
+    pub glyph: usize,
+    #[serde(default)]
+    pub color: Option<(f32, f32, f32)>,
+    #[serde(default)]
+    pub stats: Option<PawnStats>,
+    pub hit_die: HitDie,
+    #[serde(default)]
+    pub resistances: Vec<DamageType>,
+    #[serde(default)]
+    pub immunities: Vec<DamageType>,
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    #[serde(default)]
+    pub on_hit: Option<(StatusKind, u32)>,
+    #[serde(default)]
+    pub behaviour: Behaviour,
+    #[serde(default)]
+    pub hire: Option<Hire>,
+    #[serde(default)]
+    pub tame: Option<Hire>,
+    #[serde(default)]
+    pub brain: Option<String>,
+}
+
+// NOTE: Component that holds the name of the archetype a pawn is spawned from.
+#[derive(Component, Debug, Clone)]
+pub struct ArchetypeName(pub String);
+
+// NOTE: Resource which holds every loaded archetype by their names.
+#[derive(Resource, Default)]
+pub struct ArchetypeRegistry {
+    pub archetypes: HashMap<String, Archetype>,
+}
+
+impl ArchetypeRegistry {
+    pub fn get(&self, name: &str) -> Option<&Archetype> {
+        return self.archetypes.get(name);
+    }
+
+    // NOTE: Returns every archetype the player can recruit, sorted by name.
+    pub fn recruitable(&self) -> Vec<&Archetype> {
+        let mut result: Vec<&Archetype> = self.archetypes.values()
+            .filter(|a| a.hire.is_some())
+            .collect();
+
+        result.sort_by(|a, b| a.name.cmp(&b.name));
+
+        return result;
+    }
+}
+
+// NOTE: Loads every archetype definition in the archetype
+//       folder, and creates the `ArchetypeRegistry` resource.
+fn setup_archetype_registry(mut commands: Commands) {
+    let path = FileAssetIo::get_base_path().join(ARCHETYPE_FOLDER);
+
+    let entries = match fs::read_dir(&path) {
+        Ok(e) => e,
+        Err(e) => {
+            error!("Failed to read the archetype folder `{}`: {}.", path.display(), e);
+            panic!();
+        }
+    };
+
+    let mut registry = ArchetypeRegistry::default();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.extension().map_or(true, |e| e != ARCHETYPE_EXTENSION) {
+            continue;
+        }
+
+        let source = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Failed to read the archetype file `{}`: {}.", path.display(), e);
+                panic!();
+            }
+        };
+
+        let archetype: Archetype = match ron::from_str(&source) {
+            Ok(a) => a,
+            Err(e) => {
+                error!("Failed to parse the archetype file `{}`: {}.", path.display(), e);
+                panic!();
+            }
+        };
+
+        info!("Loaded archetype `{}`.", archetype.name);
+
+        registry.archetypes.insert(archetype.name.clone(), archetype);
+    }
+
+    commands.insert_resource(registry);
+}
+
+// NOTE: Spawns a pawn from the archetype with the given name, this is
+//       the preffered way of spawning a pawn. Returns `None` if no
+//       archetype with the given name exists.
+pub fn spawn_pawn_from_archetype(
+    commands: &mut Commands,
+    world: &mut world::World,
+    tileset: &tileset::Tileset,
+    registry: &ArchetypeRegistry,
+    name: &str,
+    position: (usize, usize, usize),
+    alignment: Alignment,
+    rng: &mut GameRng,
+) -> Option<Entity> {
+    let archetype = match registry.get(name) {
+        Some(a) => a,
+        None => {
+            error!("Failed to find an archetype with the name `{}`.", name);
+            return None;
+        }
+    };
+
+    let color = match archetype.color {
+        Some((r, g, b)) => Color::rgb(r, g, b),
+        None => alignment.color(),
+    };
+
+    let stats = match archetype.stats {
+        Some(s) => s,
+        None => PawnStats::roll(rng.stream(RngStream::Stats)),
+    };
+
+    let e = core::spawn_default_pawn(
+        commands, world, tileset, archetype.glyph, position, color, stats, archetype.hit_die, rng
+    );
+
+    commands.entity(e)
+        .insert(alignment)
+        .insert(Resistance {
+            resistances: archetype.resistances.clone(),
+            immunities: archetype.immunities.clone(),
+        })
+        .insert(Abilities(archetype.abilities.clone()))
+        .insert(SpellCooldowns::default())
+        .insert(ArchetypeName(archetype.name.clone()));
+
+    if let Some(brain) = &archetype.brain {
+        commands.entity(e).insert(Brain(brain.clone()));
+    }
+
+    if let Some((kind, turns)) = archetype.on_hit {
+        commands.entity(e).insert(OnHitEffect(kind, turns));
+    }
+
+    match archetype.behaviour {
+        Behaviour::Idle => {},
+        Behaviour::Worker => {
+            commands.entity(e).insert(worker::Worker::default());
+        },
+    }
+
+    return Some(e);
+}
@@ -1,6 +1,8 @@
 use bevy::prelude::*;
-use log::error;
+use serde::Deserialize;
+use log::{info, error};
 
+use crate::{world, util::prelude::*};
 use super::prelude::*;
 
 pub mod prelude {
@@ -9,6 +11,10 @@ pub mod prelude {
         Health,
         DamageType,
         Resistance,
+        AttackKind,
+        AttackEvent,
+        DamageEvent,
+        HealEvent,
     };
 }
 
@@ -16,19 +22,40 @@ pub struct CombatPlugin;
 
 impl Plugin for CombatPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_to_stage(CoreStage::PreUpdate, setup_health);
+        app.add_event::<AttackEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<HealEvent>()
+            .add_system_to_stage(CoreStage::PreUpdate, setup_health)
+            .add_system_to_stage(CoreStage::PostUpdate, resolve_attacks)
+            .add_system_to_stage(CoreStage::PostUpdate, apply_damage.after(resolve_attacks));
     }
 }
 
+// NOTE: Base armor class of every pawn, dexterity bonus is added on top of it.
+pub const BASE_ARMOR_CLASS: i32 = 10;
+
+// NOTE: Die that is rolled for unarmed melee damage.
+pub const MELEE_DAMAGE_DIE: Die = Die::D6(0);
+
+// NOTE: Die that is rolled for ranged damage.
+pub const RANGED_DAMAGE_DIE: Die = Die::D4(0);
+
+// NOTE: Maximum distance of a ranged attack in tiles.
+pub const RANGED_ATTACK_RANGE: u32 = 8;
+
 #[allow(dead_code)]
 // NOTE: All possible damage types, as a rule
 //       no pawn should be resistant to force damage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 pub enum DamageType {
     Invalid,
     Slashing,
+    Piercing,
+    Bludgeoning,
     Fire,
     Cold,
     Acid,
+    Poison,
     Thunder,
     Lightning,
     Radiant,
@@ -36,7 +63,7 @@ pub enum DamageType {
     Force,
 }
 
-#[derive(Component)]
+#[derive(Component, Debug, Clone, Default)]
 // NOTE: Contains the damage resistances and immunities of an entity.
 pub struct Resistance {
     pub resistances: Vec<DamageType>,
@@ -45,6 +72,7 @@ pub struct Resistance {
 
 #[allow(dead_code)]
 // NOTE: Die that is used to determine a pawn's health.
+#[derive(Debug, Clone, Copy, Deserialize)]
 pub enum HitDie {
     D4,
     D6,
@@ -74,6 +102,18 @@ pub struct Health {
     pub maximum: i32,
 }
 
+impl Health {
+    // NOTE: Creates a health component with the given hit die, maximum
+    //       and current health are calculated when the component is added.
+    pub fn new(hit_die: HitDie) -> Self {
+        Self {
+            hit_die,
+            current: 0,
+            maximum: 0,
+        }
+    }
+}
+
 // NOTE: Sets up the health component when its first added to a pawn,
 //       also checks if that pawn has `PawnStats` component which is required for `Health`.
 fn setup_health(mut query: Query<(&mut Health, Option<&PawnStats>), (With<Pawn>, Added<Health>)>) {
@@ -85,7 +125,200 @@ fn setup_health(mut query: Query<(&mut Health, Option<&PawnStats>), (With<Pawn>,
             panic!();
         };
 
-        health.maximum = health.hit_die.value() as i32 + get_stat_bonus(vitality);
+        health.maximum = (health.hit_die.value() as i32 + get_stat_bonus(vitality)).max(1);
         health.current = health.maximum;
     }
+}
+
+// NOTE: Kinds of weapon attacks a pawn can perform.
+//       - Melee  -> Adjacent targets, uses strenght and melee skill.
+//       - Ranged -> Targets in `RANGED_ATTACK_RANGE` with a line of sight, uses dexterity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackKind {
+    Melee,
+    Ranged,
+}
+
+// NOTE: Event that is sent when a pawn attacks another pawn.
+#[derive(Clone, Copy)]
+pub struct AttackEvent {
+    pub attacker: Entity,
+    pub target: Entity,
+    pub kind: AttackKind,
+}
+
+// NOTE: Event that is sent when a pawn takes damage, the amount is
+//       reduced by the target's `Resistance` before it's applied.
+//       Source is the faction that dealt the damage, if there is any.
+#[derive(Clone, Copy)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: i32,
+    pub damage_type: DamageType,
+    pub source: Option<Alignment>,
+}
+
+// NOTE: Event that is sent when a pawn is healed.
+#[derive(Clone, Copy)]
+pub struct HealEvent {
+    pub target: Entity,
+    pub amount: i32,
+}
+
+impl Resistance {
+    // NOTE: Returns the damage after resistances and immunities are applied,
+    //       resistances halve the damage and immunities negate it. As a rule
+    //       force damage is never reduced.
+    pub fn apply(&self, amount: i32, damage_type: DamageType) -> i32 {
+        if damage_type == DamageType::Force {
+            return amount;
+        }
+
+        if self.immunities.contains(&damage_type) {
+            return 0;
+        }
+
+        if self.resistances.contains(&damage_type) {
+            return amount / 2;
+        }
+
+        return amount;
+    }
+}
+
+// NOTE: Returns whether the target is in reach for the given attack kind.
+pub fn in_attack_range(
+    kind: AttackKind,
+    attacker: &Position,
+    target: &Position,
+    world: &world::World,
+) -> bool {
+    match kind {
+        AttackKind::Melee => attacker.distance(target) <= 2,
+        AttackKind::Ranged => {
+            attacker.distance(target) <= RANGED_ATTACK_RANGE.pow(2)
+                && world.has_line_of_sight((*attacker).into(), (*target).into())
+        },
+    }
+}
+
+// NOTE: Resolves weapon attacks, the attacker rolls a d20 against the target's
+//       armor class, and deals damage on a hit. Neglected needs and status
+//       effects change the advantage, and melee skill grants a hit bonus.
+pub fn resolve_attacks(
+    query: Query<(
+        &Position,
+        &PawnStats,
+        Option<&Needs>,
+        Option<&Skills>,
+        Option<&StatusEffects>,
+        Option<&OnHitEffect>,
+        Option<&Alignment>,
+    ), With<Pawn>>,
+    mut event_reader: EventReader<AttackEvent>,
+    mut damage_ew: EventWriter<DamageEvent>,
+    mut status_ew: EventWriter<ApplyStatusEvent>,
+    mut rng: ResMut<GameRng>,
+    world: Res<world::World>,
+) {
+    let rng = rng.stream(RngStream::Combat);
+
+    for e in event_reader.iter() {
+        let [attacker, target] = match query.get_many([e.attacker, e.target]) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        if !in_attack_range(e.kind, attacker.0, target.0, &world) {
+            info!("Attack target is out of reach, attack is skipped.");
+            continue;
+        }
+
+        let mut sources = vec![];
+
+        if let Some(needs) = attacker.2 {
+            sources.push(needs.advantage());
+        }
+
+        if let Some(effects) = attacker.4 {
+            sources.extend(effects.advantages());
+        }
+
+        let advantage = Advantage::resolve(sources);
+
+        // NOTE: Melee skill grants a bonus to the melee hit roll.
+        let skill_bonus = match (e.kind, attacker.3) {
+            (AttackKind::Melee, Some(skills)) => skills.level(SkillKind::Melee) as i32 / 2,
+            _ => 0,
+        };
+
+        let (bonus, damage_die, damage_type) = match e.kind {
+            AttackKind::Melee => (get_stat_bonus(attacker.1.strenght), MELEE_DAMAGE_DIE, DamageType::Slashing),
+            AttackKind::Ranged => (get_stat_bonus(attacker.1.dexterity), RANGED_DAMAGE_DIE, DamageType::Piercing),
+        };
+
+        let hit = roll(1, Die::D20(0), bonus + skill_bonus, advantage, rng);
+        let armor_class = BASE_ARMOR_CLASS + get_stat_bonus(target.1.dexterity);
+
+        if hit.total < armor_class {
+            continue;
+        }
+
+        let damage = roll(1, damage_die, bonus, Advantage::Normal, rng);
+
+        damage_ew.send(DamageEvent {
+            target: e.target,
+            amount: damage.total.max(1),
+            damage_type,
+            source: attacker.6.copied(),
+        });
+
+        if let Some(OnHitEffect(kind, turns)) = attacker.5 {
+            status_ew.send(ApplyStatusEvent {
+                target: e.target,
+                kind: *kind,
+                turns: *turns,
+            });
+        }
+    }
+}
+
+// NOTE: Applies damage and healing to pawns, pawns that
+//       drop to zero health are removed from the world.
+pub fn apply_damage(
+    mut commands: Commands,
+    mut query: Query<(&Position, &mut Health, Option<&Resistance>), With<Pawn>>,
+    mut damage_er: EventReader<DamageEvent>,
+    mut heal_er: EventReader<HealEvent>,
+    mut world: ResMut<world::World>,
+) {
+    for e in heal_er.iter() {
+        if let Ok((_, mut health, _)) = query.get_mut(e.target) {
+            health.current = (health.current + e.amount).min(health.maximum);
+        }
+    }
+
+    for e in damage_er.iter() {
+        let (position, mut health, resistance) = match query.get_mut(e.target) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        // NOTE: Skip pawns that are already dead this frame.
+        if health.current <= 0 {
+            continue;
+        }
+
+        let amount = match resistance {
+            Some(r) => r.apply(e.amount, e.damage_type),
+            None => e.amount,
+        };
+
+        health.current -= amount;
+
+        if health.current <= 0 {
+            world.set_entity((*position).into(), None);
+            commands.entity(e.target).despawn_recursive();
+        }
+    }
 }
\ No newline at end of file
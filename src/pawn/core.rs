@@ -1,10 +1,12 @@
 use bevy_egui::egui::Color32;
 use rand::Rng;
-use std::collections::VecDeque;
+use std::{collections::VecDeque, cell::Cell};
 use bevy::prelude::*;
 use pathfinding::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::cmp::Reverse;
 
-use crate::{world, tileset, globals, turn_system};
+use crate::{world::{self, structure::{DoorAccess, DOOR_BREAK_COST}}, tileset, globals, turn_system, util::prelude::*};
 use super::{prelude::*, turn, name, worker};
 
 pub mod prelude {
@@ -28,9 +30,6 @@ impl Plugin for CorePlugin {
 // NOTE: Highlight color used for selected pawns without `Alignment`.
 const DEFAULT_PAWN_HIGHLIGHT_COLOR: Color = Color::WHITE;
 
-// NOTE: Default glyph for any pawn.
-pub const DEFAULT_PAWN_GLYPH: usize = 2;
-
 // NOTE: A tag that is required for every pawn to have.
 #[derive(Component)]
 pub struct Pawn;
@@ -45,29 +44,82 @@ pub struct Selectable {
 
 // NOTE: Required for every pawn that posseses a position
 //       in the world, also used for A* pathfinding algorithm.
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Component)]
+//       `z` is the level of the position, zero is the surface.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Component, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
+    #[serde(default)]
+    pub z: i32,
 }
 
+// NOTE: Distance that a single level counts as, when
+//       calculating the distance between two positions.
+const LEVEL_DISTANCE: i32 = 10;
+
+// NOTE: Base cost of a single straight and diagonal move in pathfinding,
+//       the ratio between them approximates √2. Both are multiplied by
+//       the movement cost of the tile that is moved into.
+pub const MOVEMENT_COST_STRAIGHT: u32 = 10;
+pub const MOVEMENT_COST_DIAGONAL: u32 = 14;
+
+// NOTE: Cost of the cheapest passage, used for the pathfinding heuristic.
+const MINIMUM_PASSAGE_COST: u32 = 1;
+
+// NOTE: Maximum number of tiles a single path search expands, searches for
+//       unreachable targets give up instead of flooding every connected tile
+//       of the map. Targets that take more are unreachable until the pawn
+//       gets closer to them.
+pub const MAX_PATH_SEARCH_NODES: usize = 20_000;
+
 #[allow(dead_code)]
 impl Position {
-    pub fn new(x: i32, y: i32) -> Self {
-        return Self {x, y};
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        return Self {x, y, z};
     }
 
     // NOTE: Returns the approximate distance from self to
     //       the other by using the formula x^2 + b^2 = c^2.
+    //       Positions on different levels are further apart.
     pub fn distance(&self, other: &Position) -> u32 {
-        return ((self.x - other.x).pow(2) + (self.y - other.y).pow(2)) as u32;
+        return (
+            (self.x - other.x).pow(2) +
+            (self.y - other.y).pow(2) +
+            ((self.z - other.z) * LEVEL_DISTANCE).pow(2)
+        ) as u32;
     }
 
-    // NOTE: Returns the every possible adjected successor position.
-    pub fn successors(&self, world: &world::World) -> Vec<(Position, u32)> {
+    // NOTE: Returns the estimated cost of the cheapest path from self to
+    //       the other, every straight step costs at least one straight move
+    //       and every diagonal step at least one diagonal move. Each level
+    //       in between takes at least the cheapest passage.
+    pub fn path_heuristic(&self, other: &Position) -> u32 {
+        let dx = (self.x - other.x).unsigned_abs();
+        let dy = (self.y - other.y).unsigned_abs();
+        let dz = (self.z - other.z).unsigned_abs();
+
+        let diagonal = dx.min(dy);
+        let straight = dx.max(dy) - diagonal;
+
+        return diagonal * MOVEMENT_COST_DIAGONAL
+            + straight * MOVEMENT_COST_STRAIGHT
+            + dz * MINIMUM_PASSAGE_COST * MOVEMENT_COST_STRAIGHT;
+    }
+
+    // NOTE: Returns the every possible adjected successor position, with
+    //       the cost of moving into it. Diagonal moves cost about √2 times a
+    //       straight move, and can't cut the corner of a tile that isn't
+    //       walkable. The cost of the tile that is moved into is included,
+    //       doors are passed depending on the alignment of the moving pawn.
+    pub fn successors(
+        &self,
+        world: &world::World,
+        alignment: Option<&Alignment>,
+        factions: &Factions,
+    ) -> Vec<(Position, u32)> {
         // NOTE: Create a vector to store possible successors.
-        let mut vec: Vec<Position> = Vec::with_capacity(8);
-        
+        let mut vec: Vec<(Position, u32)> = Vec::with_capacity(8);
+
         for y in -1..=1i32 {
             for x in -1..=1i32 {
                 // NOTE: Skip the self.
@@ -77,42 +129,81 @@ impl Position {
 
                 // NOTE: Calculate the possition for the successor,
                 //       skip it if either x or y is below zero.
-                let pos: (usize, usize) = (
-                    if (self.x + x) < 0 { continue; } else { 
-                        (self.x + x) as usize 
-                    }, 
-                    if (self.y + y) < 0 { continue; } else { 
-                        (self.y + y) as usize 
+                let pos: (usize, usize, usize) = (
+                    if (self.x + x) < 0 { continue; } else {
+                        (self.x + x) as usize
+                    },
+                    if (self.y + y) < 0 { continue; } else {
+                        (self.y + y) as usize
                     },
+                    self.z as usize,
                 );
-                 
-                // NOTE: If the successor is not a solid tile and if
-                //       it is not occupied, add it to the vector.
-                if !world.is_solid_tile(pos) && world.get_entity(pos).is_none() {
-                    vec.push(pos.into());
+
+                // NOTE: If the successor is not a passable tile
+                //       or if it is occupied, skip it.
+                if !world.is_passable_tile(pos, alignment, factions) || world.get_entity(pos).is_some() {
+                    continue;
                 }
+
+                let diagonal = x != 0 && y != 0;
+
+                // NOTE: Pawns can't squeeze between corners, both tiles
+                //       next to a diagonal move have to be walkable.
+                if diagonal && (
+                    !world.is_walkable_tile((pos.0, self.y as usize, pos.2))
+                    || !world.is_walkable_tile((self.x as usize, pos.1, pos.2))
+                ) {
+                    continue;
+                }
+
+                let step = if diagonal { MOVEMENT_COST_DIAGONAL } else { MOVEMENT_COST_STRAIGHT };
+
+                vec.push((pos.into(), step * world.get_tile(pos).movement_cost() + door_cost(world, pos, alignment, factions)));
+            }
+        }
+
+        // NOTE: A passage on the tile leads to the level above or below.
+        if let Some(target) = world.passage_target((*self).into()) {
+            if world.is_passable_tile(target, alignment, factions) && world.get_entity(target).is_none() {
+                let passage = world.get_tile((*self).into()).passage.unwrap();
+
+                vec.push((target.into(), passage.cost() * MOVEMENT_COST_STRAIGHT + door_cost(world, target, alignment, factions)));
             }
         }
 
-        return vec.into_iter().map(|p| (p, 1)).collect();
+        return vec;
     }
 }
 
-impl From<(usize, usize)> for Position {
-    fn from(p: (usize, usize)) -> Self {
-        return Self { x: p.0 as i32, y: p.1 as i32 };
+// NOTE: Returns the extra cost of moving into a tile, doors that
+//       have to be broken down take a while to get through.
+fn door_cost(
+    world: &world::World,
+    pos: (usize, usize, usize),
+    alignment: Option<&Alignment>,
+    factions: &Factions,
+) -> u32 {
+    return match world.get_tile(pos).door {
+        Some(door) if world.door_access(&door, alignment, factions) == DoorAccess::Break => DOOR_BREAK_COST * MOVEMENT_COST_STRAIGHT,
+        _ => 0,
+    };
+}
+
+impl From<(usize, usize, usize)> for Position {
+    fn from(p: (usize, usize, usize)) -> Self {
+        return Self { x: p.0 as i32, y: p.1 as i32, z: p.2 as i32 };
     }
 }
 
-impl From<Position> for (usize, usize) {
+impl From<Position> for (usize, usize, usize) {
     fn from(p: Position) -> Self {
-        return (p.x as usize , p.y as usize);
+        return (p.x as usize , p.y as usize, p.z as usize);
     }
 }
 
 // NOTE: Alignment component, determines who owns a pawn.
 #[allow(dead_code)]
-#[derive(Component, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Alignment {
     Neutral = -1,
     Player = 1,
@@ -154,15 +245,13 @@ impl Alignment {
 }
 
 // NOTE: Picks a random pawn name from pawn name table.
-pub fn pick_random_pawn_name() -> &'static str {
-    let mut rng = rand::thread_rng();
-
-    let name = rng.gen_range(0..name::PAWN_NAMES.len());
+pub fn pick_random_pawn_name(rng: &mut GameRng) -> &'static str {
+    let name = rng.stream(RngStream::Names).gen_range(0..name::PAWN_NAMES.len());
     
     return name::PAWN_NAMES[name];
 }
 
-// NOTE: Spawns a default pawn with given location and color.
+// NOTE: Spawns a default pawn with given location, color, stats and hit die.
 //       Default pawn components:
 //       - Pawn
 //       - Position
@@ -171,13 +260,18 @@ pub fn pick_random_pawn_name() -> &'static str {
 //       - Name
 //       - PawnStats
 //       - Health
+//       - Needs
+//       - Skills
 pub fn spawn_default_pawn(
     commands: &mut Commands,
     world: &mut world::World,
     tileset: &tileset::Tileset,
     glyph: usize,
-    position: (usize, usize),
+    position: (usize, usize, usize),
     color: Color,
+    stats: PawnStats,
+    hit_die: HitDie,
+    rng: &mut GameRng,
 ) -> Entity {
     // NOTE: Create a basic tileset sprite entity.
     let e = tileset::spawn_sprite_from_tileset(
@@ -205,13 +299,13 @@ pub fn spawn_default_pawn(
             selected: false,
             original_color: color,
         })
-        .insert(Name::new(pick_random_pawn_name()))
-        .insert(PawnStats::default())
-        .insert(Health {
-            hit_die: HitDie::D6,
-            current: 100,
-            maximum: 100,
-        });
+        .insert(Name::new(pick_random_pawn_name(rng)))
+        .insert(stats)
+        .insert(Health::new(hit_die))
+        .insert(Needs::default())
+        .insert(Skills::default())
+        .insert(StatusEffects::default())
+        .insert(Initiative::default());
 
     // NOTE: Insert entity into world.
     world.set_entity(position, Some(e));
@@ -219,34 +313,17 @@ pub fn spawn_default_pawn(
     return e;
 }
 
-// NOTE: Spawns a default entity with an alignment, this
-//       is the preffered way of spawning an entity.
-pub fn spawn_default_pawn_with_alignment(
-    commands: &mut Commands,
-    world: &mut world::World,
-    tileset: &tileset::Tileset,
-    glyph: usize,
-    position: (usize, usize),
-    alignment: Alignment,
-) -> Entity {
-    let e = spawn_default_pawn(
-        commands, world, tileset, glyph, position, alignment.color()
-    );
-
-    commands.entity(e).insert(alignment);
-
-    return e;
-}
-
 // NOTE: Moves a given pawn to a tile in world.
 pub fn move_pawn(
-    target: (usize, usize),
+    target: (usize, usize, usize),
     entity: Entity,
     transform: &mut Transform,
     position: &mut Position,
     world: &mut world::World,
+    alignment: Option<&Alignment>,
+    factions: &Factions,
 ) -> bool {
-    if !world.is_solid_tile(target) && world.get_entity(target).is_none() {
+    if world.is_walkable_tile(target) && !world.is_solid_tile(target, alignment, factions) && world.get_entity(target).is_none() {
         // NOTE: Erase past position from the world
         world.set_entity((*position).into(), None);
 
@@ -259,6 +336,7 @@ pub fn move_pawn(
         // NOTE: Set pawn's position component.
         position.x = target.0 as i32;
         position.y = target.1 as i32;
+        position.z = target.2 as i32;
 
         return true;
     }
@@ -273,44 +351,138 @@ pub fn pawn_find_path(
     position: Position,
     target: Position,
     world: &world::World,
+    alignment: Option<&Alignment>,
+    factions: &Factions,
 ) -> Option<(Vec<Position>, u32)> {
+    return find_path_bounded(
+        position,
+        |p| p.path_heuristic(&target),
+        |p| *p == target,
+        world,
+        alignment,
+        factions,
+    );
+}
+
+// NOTE: Finds the shortest path from the position to the first tile the
+//       success holds for, the heuristic must not overestimate the cost.
+//       The search gives up after expanding `MAX_PATH_SEARCH_NODES` tiles.
+pub fn find_path_bounded(
+    position: Position,
+    heuristic: impl Fn(&Position) -> u32,
+    success: impl Fn(&Position) -> bool,
+    world: &world::World,
+    alignment: Option<&Alignment>,
+    factions: &Factions,
+) -> Option<(Vec<Position>, u32)> {
+    let expanded = Cell::new(0);
+
     return astar(
-        &position, 
-        |p| p.successors(&world),
-        |p| p.distance(&target), 
-        |p| *p == target
+        &position,
+        |p| {
+            expanded.set(expanded.get() + 1);
+
+            // NOTE: Out of budget, the remaining open tiles are dead ends.
+            if expanded.get() > MAX_PATH_SEARCH_NODES {
+                return vec![];
+            }
+
+            return p.successors(world, alignment, factions);
+        },
+        heuristic,
+        success,
     );
 }
 
-// NOTE: Process every pawns turn.
-fn process_pawn_turns(
-    mut query: Query<(Entity, &mut TaskQueue, &mut Transform, &mut Position), With<Pawn>>,
+// NOTE: Process every pawns turn, pawns act in the order of their initiative
+//       which is rolled every turn. Depending on their speed pawns may act
+//       more than once, or not at all in a single turn.
+pub fn process_pawn_turns(
+    mut query: Query<(
+        Entity,
+        &mut TaskQueue,
+        &mut Transform,
+        &mut Position,
+        &PawnStats,
+        &mut Initiative,
+        Option<&mut Needs>,
+        Option<&mut Skills>,
+        Option<&StatusEffects>,
+        Option<&Alignment>,
+    ), With<Pawn>>,
+    mut turn_order: ResMut<TurnOrder>,
     mut world: ResMut<world::World>,
     mut global_work_pool: ResMut<worker::GlobalWorkValidator>,
     mut event_reader: EventReader<turn_system::TurnOverEvent>,
     mut mine_tile_ew: EventWriter<worker::MineTileEvent>,
+    mut build_tile_ew: EventWriter<worker::BuildTileEvent>,
+    mut attack_ew: EventWriter<AttackEvent>,
+    mut cast_ew: EventWriter<CastSpellEvent>,
+    mut rng: ResMut<GameRng>,
+    factions: Res<Factions>,
 ) {
-    let mut over = false;
-    for _ in event_reader.iter() {
-        over = true;
+    let mut turn = None;
+    for e in event_reader.iter() {
+        turn = Some(e.turn);
     }
 
     // NOTE: Return early until the turn is over.
-    if !over {
-        return;
+    let turn = match turn {
+        Some(t) => t,
+        None => return,
+    };
+
+    let rng = rng.stream(RngStream::AI);
+
+    // NOTE: Roll the initiatives in the order of entities,
+    //       so the rolls don't depend on the query order.
+    let mut entities: Vec<Entity> = query.iter().map(|q| q.0).collect();
+    entities.sort();
+
+    let mut order: Vec<(Entity, i32, u32)> = vec![];
+
+    for entity in entities {
+        if let Ok((_, _, _, _, stats, mut initiative, ..)) = query.get_mut(entity) {
+            initiative.roll(stats, rng);
+            order.push((entity, initiative.value, stats.dexterity));
+        }
     }
 
+    order.sort_by_key(|(e, i, d)| (Reverse(*i), Reverse(*d), *e));
+
+    turn_order.turn = turn;
+    turn_order.order = order.iter().map(|(e, i, _)| (*e, *i)).collect();
+
+    debug!("Turn order for turn {}: {:?}.", turn, turn_order.order);
+
     // NOTE: Act the pawns turns
-    for (entity, mut queue, mut transform, mut position) in &mut query {
-        turn::pawn_act_turn(
-            entity,
-            &mut queue,
-            &mut transform,
-            &mut position,
-            &mut world,
-            &mut global_work_pool,
-            &mut mine_tile_ew,
-        );
+    for (entity, _, _) in order {
+        let (_, mut queue, mut transform, mut position, stats, mut initiative, mut needs, mut skills, effects, alignment) =
+            match query.get_mut(entity) {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+
+        for _ in 0..initiative.actions(stats.speed) {
+            turn::pawn_act_turn(
+                entity,
+                &mut queue,
+                &mut transform,
+                &mut position,
+                &mut world,
+                needs.as_deref_mut(),
+                skills.as_deref_mut(),
+                effects,
+                alignment,
+                &factions,
+                rng,
+                &mut global_work_pool,
+                &mut mine_tile_ew,
+                &mut build_tile_ew,
+                &mut attack_ew,
+                &mut cast_ew,
+            );
+        }
     }
 }
 
@@ -339,4 +511,50 @@ fn highlight_selected_pawns(
             sprite.color = selectable.original_color;
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::world::tile::TileState;
+
+    #[test]
+    fn path_heuristic() {
+        let origin = Position::new(0, 0, 0);
+
+        assert_eq!(origin.path_heuristic(&Position::new(3, 0, 0)), 3 * MOVEMENT_COST_STRAIGHT);
+        assert_eq!(origin.path_heuristic(&Position::new(3, 1, 0)), MOVEMENT_COST_DIAGONAL + 2 * MOVEMENT_COST_STRAIGHT);
+        assert_eq!(origin.path_heuristic(&Position::new(2, 2, 1)), 2 * MOVEMENT_COST_DIAGONAL + MOVEMENT_COST_STRAIGHT);
+    }
+
+    #[test]
+    fn successor_costs() {
+        let world = world::test_world(TileState::Empty);
+        let successors = Position::new(10, 10, 0).successors(&world, None, &Factions::default());
+
+        assert_eq!(successors.len(), 8);
+
+        for (p, cost) in successors {
+            let diagonal = p.x != 10 && p.y != 10;
+
+            assert_eq!(cost, if diagonal { 14 } else { 10 });
+        }
+    }
+
+    #[test]
+    fn no_corner_cutting() {
+        let mut world = world::test_world(TileState::Empty);
+        world.get_tile_mut((11, 10, 0)).state = TileState::Solid;
+
+        let successors: Vec<Position> = Position::new(10, 10, 0).successors(&world, None, &Factions::default())
+            .into_iter()
+            .map(|(p, _)| p)
+            .collect();
+
+        assert_eq!(successors.len(), 5);
+        assert!(!successors.contains(&Position::new(11, 10, 0)));
+        assert!(!successors.contains(&Position::new(11, 11, 0)));
+        assert!(!successors.contains(&Position::new(11, 9, 0)));
+        assert!(successors.contains(&Position::new(9, 11, 0)));
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, VecDeque};
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{world, turn_system};
+use super::{prelude::*, core, combat, spell};
+
+pub mod prelude {
+    pub use super::{
+        Relation,
+        Factions,
+        Provoked,
+    };
+}
+
+pub struct FactionPlugin;
+
+impl Plugin for FactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Factions>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                provoke_attacked_pawns.after(combat::resolve_attacks).after(spell::resolve_spells)
+            )
+            .add_system_to_stage(CoreStage::Update, calm_provoked_pawns);
+    }
+}
+
+// NOTE: Number of turns a provoked pawn stays hostile to its attackers.
+pub const PROVOKED_TURNS: u32 = 20;
+
+// NOTE: Chance of an idle neutral pawn wandering off every turn.
+pub const WANDER_CHANCE: f64 = 0.25;
+
+// NOTE: Maximum distance in tiles a neutral pawn wanders at once.
+pub const WANDER_RADIUS: i32 = 4;
+
+// NOTE: Distance in tiles a provoked pawn that can't fight tries to flee.
+pub const FLEE_RADIUS: i32 = 6;
+
+// NOTE: Every relation two factions can have.
+//       - Hostile -> Attack each other on sight.
+//       - Neutral -> Leave each other alone, until one is attacked.
+//       - Allied  -> Fight on the same side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Hostile,
+    Neutral,
+    Allied,
+}
+
+// NOTE: Resource that holds the relations between the factions, every
+//       `Alignment` is a faction. Relations are symmetric, factions are
+//       allied with themselves and neutral unless told otherwise.
+#[derive(Resource, Debug, Clone)]
+pub struct Factions {
+    pub relations: BTreeMap<(Alignment, Alignment), Relation>,
+}
+
+impl Default for Factions {
+    fn default() -> Self {
+        let mut factions = Self { relations: BTreeMap::new() };
+
+        factions.set(Alignment::Player, Alignment::Enemy, Relation::Hostile);
+        factions.set(Alignment::Player, Alignment::Neutral, Relation::Neutral);
+        factions.set(Alignment::Enemy, Alignment::Neutral, Relation::Neutral);
+
+        return factions;
+    }
+}
+
+impl Factions {
+    pub fn relation(&self, a: Alignment, b: Alignment) -> Relation {
+        if a == b {
+            return Relation::Allied;
+        }
+
+        return *self.relations.get(&(a.min(b), a.max(b))).unwrap_or(&Relation::Neutral);
+    }
+
+    pub fn set(&mut self, a: Alignment, b: Alignment, relation: Relation) {
+        self.relations.insert((a.min(b), a.max(b)), relation);
+    }
+
+    pub fn hostile(&self, a: Alignment, b: Alignment) -> bool {
+        return self.relation(a, b) == Relation::Hostile;
+    }
+}
+
+// NOTE: Component of the pawns that were attacked by a faction they
+//       are neutral to, they stay hostile to it for a number of turns.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Provoked {
+    pub by: Alignment,
+    pub turns: u32,
+}
+
+// NOTE: Returns whether the pawn treats the other pawn as hostile,
+//       either by the relation of their factions or by provocation.
+pub fn is_hostile_to(
+    factions: &Factions,
+    alignment: Alignment,
+    provoked: Option<&Provoked>,
+    other: Alignment,
+) -> bool {
+    return factions.hostile(alignment, other) || provoked.map_or(false, |p| p.by == other);
+}
+
+// NOTE: Provokes the pawns that are attacked or damaged by a faction that
+//       isn't hostile to them, spells and traps provoke them too. Attacking
+//       them again renews the provocation.
+fn provoke_attacked_pawns(
+    mut commands: Commands,
+    mut attack_er: EventReader<AttackEvent>,
+    mut damage_er: EventReader<DamageEvent>,
+    alignments: Query<&Alignment, With<Pawn>>,
+    factions: Res<Factions>,
+) {
+    let attacks = attack_er.iter().filter_map(|e| alignments.get(e.attacker).ok().map(|a| (*a, e.target)));
+    let damages = damage_er.iter().filter_map(|e| e.source.map(|a| (a, e.target)));
+
+    for (source, target) in attacks.chain(damages) {
+        let alignment = match alignments.get(target) {
+            Ok(a) => *a,
+            Err(_) => continue,
+        };
+
+        if factions.relation(source, alignment) != Relation::Neutral {
+            continue;
+        }
+
+        commands.entity(target).insert(Provoked {
+            by: source,
+            turns: PROVOKED_TURNS,
+        });
+    }
+}
+
+// NOTE: Counts down the provocations at the end of every turn,
+//       pawns calm down when their provocation runs out.
+fn calm_provoked_pawns(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Provoked)>,
+    mut event_reader: EventReader<turn_system::TurnOverEvent>,
+) {
+    let mut over = false;
+    for _ in event_reader.iter() {
+        over = true;
+    }
+
+    if !over {
+        return;
+    }
+
+    for (entity, mut provoked) in &mut query {
+        provoked.turns = provoked.turns.saturating_sub(1);
+
+        if provoked.turns == 0 {
+            commands.entity(entity).remove::<Provoked>();
+        }
+    }
+}
+
+// NOTE: Returns a path to a random walkable tile around the position.
+pub fn find_wander_path(
+    position: &Position,
+    alignment: &Alignment,
+    world: &world::World,
+    factions: &Factions,
+    rng: &mut impl Rng,
+) -> Option<MoveTask> {
+    let target = Position::new(
+        position.x + rng.gen_range(-WANDER_RADIUS..=WANDER_RADIUS),
+        position.y + rng.gen_range(-WANDER_RADIUS..=WANDER_RADIUS),
+        position.z,
+    );
+
+    if target.x < 0 || target.y < 0 || target == *position {
+        return None;
+    }
+
+    if !world.is_passable_tile(target.into(), Some(alignment), factions) || world.get_entity(target.into()).is_some() {
+        return None;
+    }
+
+    let (mut path, _) = core::pawn_find_path(*position, target, world, Some(alignment), factions)?;
+    path.remove(0);
+
+    return Some(MoveTask { path: VecDeque::from(path), target });
+}
+
+// NOTE: Returns a path to the reachable tile around the position that
+//       is farthest from the threat, ties are broken by the position.
+pub fn find_flee_path(
+    position: &Position,
+    threat: &Position,
+    alignment: &Alignment,
+    world: &world::World,
+    factions: &Factions,
+) -> Option<MoveTask> {
+    let mut candidates = vec![];
+
+    for y in -FLEE_RADIUS..=FLEE_RADIUS {
+        for x in -FLEE_RADIUS..=FLEE_RADIUS {
+            let target = Position::new(position.x + x, position.y + y, position.z);
+
+            if target.x < 0 || target.y < 0 || target.distance(threat) <= position.distance(threat) {
+                continue;
+            }
+
+            if !world.is_passable_tile(target.into(), Some(alignment), factions) || world.get_entity(target.into()).is_some() {
+                continue;
+            }
+
+            candidates.push(target);
+        }
+    }
+
+    candidates.sort_by_key(|p| (std::cmp::Reverse(p.distance(threat)), p.x, p.y));
+
+    for target in candidates {
+        if let Some((mut path, _)) = core::pawn_find_path(*position, target, world, Some(alignment), factions) {
+            path.remove(0);
+
+            return Some(MoveTask { path: VecDeque::from(path), target });
+        }
+    }
+
+    return None;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ALIGNMENTS: [Alignment; 3] = [Alignment::Player, Alignment::Enemy, Alignment::Neutral];
+
+    #[test]
+    fn relation_symmetry() {
+        let mut factions = Factions::default();
+
+        for a in ALIGNMENTS {
+            assert_eq!(factions.relation(a, a), Relation::Allied);
+
+            for b in ALIGNMENTS {
+                assert_eq!(factions.relation(a, b), factions.relation(b, a));
+            }
+        }
+
+        assert!(factions.hostile(Alignment::Enemy, Alignment::Player));
+
+        factions.set(Alignment::Neutral, Alignment::Player, Relation::Hostile);
+
+        assert_eq!(factions.relation(Alignment::Player, Alignment::Neutral), Relation::Hostile);
+        assert_eq!(factions.relation(Alignment::Neutral, Alignment::Player), Relation::Hostile);
+    }
+
+    #[test]
+    fn provoked_hostility() {
+        let factions = Factions::default();
+        let provoked = Provoked { by: Alignment::Player, turns: 1 };
+
+        assert!(!is_hostile_to(&factions, Alignment::Neutral, None, Alignment::Player));
+        assert!(is_hostile_to(&factions, Alignment::Neutral, Some(&provoked), Alignment::Player));
+        assert!(!is_hostile_to(&factions, Alignment::Neutral, Some(&provoked), Alignment::Enemy));
+    }
+
+    #[test]
+    fn provoked_by_damage() {
+        let mut world = World::new();
+        world.init_resource::<Factions>();
+        world.init_resource::<Events<AttackEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+
+        let neutral = world.spawn((Pawn, Alignment::Neutral)).id();
+        let burned = world.spawn((Pawn, Alignment::Neutral)).id();
+        let enemy = world.spawn((Pawn, Alignment::Enemy)).id();
+
+        // NOTE: Damage from a trap or a spell of the player, and from lava.
+        for (target, source) in [(neutral, Some(Alignment::Player)), (enemy, Some(Alignment::Player)), (burned, None)] {
+            world.resource_mut::<Events<DamageEvent>>().send(DamageEvent {
+                target,
+                amount: 1,
+                damage_type: DamageType::Fire,
+                source,
+            });
+        }
+
+        let mut system = IntoSystem::into_system(provoke_attacked_pawns);
+        system.initialize(&mut world);
+        system.run((), &mut world);
+        system.apply_buffers(&mut world);
+
+        assert_eq!(world.get::<Provoked>(neutral).map(|p| p.by), Some(Alignment::Player));
+        assert!(world.get::<Provoked>(burned).is_none());
+        assert!(world.get::<Provoked>(enemy).is_none());
+    }
+}
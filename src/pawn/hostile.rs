@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+
+use crate::world;
+use super::{prelude::*, spell, combat, worker, faction::{self, prelude::*}};
+
+// NOTE: Maximum distance in tiles at which a hostile pawn notices its targets.
+pub const AGGRO_RANGE: u32 = 12;
+
+// NOTE: Hostile pawns heal themselves when their health drops below this ratio.
+pub const HEAL_TRESHOLD: f32 = 0.5;
+
+// NOTE: Picks the best action for a hostile pawn against the given target,
+//       spells are preferred over ranged attacks and ranged attacks are
+//       preferred over melee attacks.
+pub fn pick_attack_tasks(
+    position: &Position,
+    target: (Entity, &Position),
+    abilities: &Abilities,
+    cooldowns: Option<&SpellCooldowns>,
+    alignment: Option<&Alignment>,
+    world: &world::World,
+    factions: &Factions,
+) -> Vec<Task> {
+    let ready = |s: &Spell| cooldowns.map_or(true, |c| c.ready(*s));
+
+    for spell in abilities.spells().iter().filter(|s| ready(s)) {
+        if spell.damage_type().is_some() && spell::can_cast(*spell, position, target.1, world) {
+            return vec![Task::Cast((*spell, *target.1))];
+        }
+    }
+
+    if abilities.has(Ability::Ranged)
+        && combat::in_attack_range(AttackKind::Ranged, position, target.1, world) {
+        return vec![Task::Shoot(target.0)];
+    }
+
+    if abilities.has(Ability::Melee) {
+        if combat::in_attack_range(AttackKind::Melee, position, target.1, world) {
+            return vec![Task::Attack(target.0)];
+        }
+
+        if let Some(mt) = worker::find_best_path_to_target(position, target.1, world, alignment, factions) {
+            return vec![Task::Move(mt), Task::Attack(target.0)];
+        }
+    }
+
+    return vec![];
+}
+
+// NOTE: Returns the closest pawn in `AGGRO_RANGE` the pawn is hostile
+//       to, either by faction or provocation. Ties are broken by entity.
+pub fn find_threat(
+    position: &Position,
+    alignment: Alignment,
+    provoked: Option<&Provoked>,
+    pawns: &[(Entity, Position, Alignment)],
+    factions: &Factions,
+) -> Option<(Entity, Position)> {
+    return pawns.iter()
+        .filter(|(_, p, _)| p.z == position.z)
+        .filter(|(_, _, a)| faction::is_hostile_to(factions, alignment, provoked, *a))
+        .map(|(e, p, _)| (*e, *p, p.distance(position)))
+        .filter(|(_, _, d)| *d <= AGGRO_RANGE.pow(2))
+        .min_by_key(|(e, _, d)| (*d, *e))
+        .map(|(e, p, _)| (e, p));
+}
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::util::prelude::*;
+use super::prelude::*;
+
+pub mod prelude {
+    pub use super::{
+        Initiative,
+        TurnOrder,
+    };
+}
+
+pub struct InitiativePlugin;
+
+impl Plugin for InitiativePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TurnOrder>();
+    }
+}
+
+// NOTE: Energy it takes for a pawn to act once, pawns gain energy equal
+//       to their speed every turn. A pawn with `DEFAULT_PAWN_SPEED` acts
+//       once per turn, twice as fast pawns act twice, and half as fast
+//       pawns act every other turn.
+pub const ACTION_COST: u32 = 10;
+
+// NOTE: Maximum number of times a pawn can act in a single turn.
+pub const MAXIMUM_ACTIONS: u32 = 4;
+
+// NOTE: Holds the initiative a pawn rolled for the current
+//       turn, and the energy it saved up for its next action.
+#[derive(Component, Debug, Default)]
+pub struct Initiative {
+    pub value: i32,
+    pub energy: u32,
+}
+
+impl Initiative {
+    // NOTE: Rolls a d20 with the dexterity bonus of the pawn.
+    pub fn roll<R: Rng + ?Sized>(&mut self, stats: &PawnStats, rng: &mut R) {
+        let bonus = get_stat_bonus(stats.dexterity);
+
+        self.value = roll(1, Die::D20(0), bonus, Advantage::Normal, rng).total;
+    }
+
+    // NOTE: Gains energy for the turn, and returns how many times the pawn acts.
+    pub fn actions(&mut self, speed: u32) -> u32 {
+        self.energy += speed;
+
+        let actions = (self.energy / ACTION_COST).min(MAXIMUM_ACTIONS);
+
+        // NOTE: Energy that exceeds the maximum actions is lost.
+        self.energy = (self.energy - actions * ACTION_COST).min(ACTION_COST - 1);
+
+        return actions;
+    }
+}
+
+// NOTE: Resource that holds the order pawns acted in during the last
+//       turn and their initiatives, pawns with higher initiative act
+//       first. Ties are broken by dexterity and then by entity.
+#[derive(Resource, Debug, Default)]
+pub struct TurnOrder {
+    pub turn: u64,
+    pub order: Vec<(Entity, i32)>,
+}
+
+impl TurnOrder {
+    // NOTE: Returns the place of the pawn in the turn order, starting from one.
+    pub fn place(&self, entity: Entity) -> Option<usize> {
+        return self.order.iter().position(|(e, _)| *e == entity).map(|i| i + 1);
+    }
+}
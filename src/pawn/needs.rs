@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+use bevy::prelude::*;
+use log::info;
+
+use crate::{
+    world::{self, room::prelude::*},
+    util::prelude::*,
+    turn_system,
+};
+use super::{prelude::*, core};
+
+pub mod prelude {
+    pub use super::Needs;
+}
+
+pub struct NeedsPlugin;
+
+impl Plugin for NeedsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::Update, decay_needs);
+    }
+}
+
+// NOTE: Maximum value of every need, a need at this value is fully satisfied.
+pub const NEED_MAXIMUM: f32 = 100.0;
+
+// NOTE: How much each need decays every turn.
+pub const HUNGER_DECAY_RATE: f32 = 0.20;
+pub const REST_DECAY_RATE: f32 = 0.15;
+
+// NOTE: How much morale changes every turn, depending
+//       on whether the pawn's other needs are met or not.
+pub const MORALE_RECOVERY_RATE: f32 = 0.05;
+pub const MORALE_DECAY_RATE: f32 = 0.25;
+
+// NOTE: Value below which a pawn will interrupt its work to satisfy a need.
+pub const HUNGER_TRESHOLD: f32 = 30.0;
+pub const REST_TRESHOLD: f32 = 25.0;
+
+// NOTE: Value below which a need is considered neglected,
+//       neglected needs hurt work speed and combat rolls.
+pub const NEGLECTED_TRESHOLD: f32 = 15.0;
+
+// NOTE: How much rest is restored every turn spent sleeping.
+pub const SLEEP_RESTORE_RATE: f32 = 2.0;
+
+// NOTE: Work speed multiplier applied for every neglected need.
+pub const NEGLECTED_WORK_SPEED: f32 = 0.5;
+
+// NOTE: Needs of a pawn, every need is kept between zero
+//       and `NEED_MAXIMUM` and higher values are better.
+//       - Hunger -> Restored by eating at a stockpile.
+//       - Rest   -> Restored by sleeping in a dormitory.
+//       - Morale -> Recovers while other needs are met, decays otherwise.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Needs {
+    pub hunger: f32,
+    pub rest: f32,
+    pub morale: f32,
+}
+
+impl Default for Needs {
+    fn default() -> Self {
+        Self {
+            hunger: NEED_MAXIMUM,
+            rest: NEED_MAXIMUM,
+            morale: NEED_MAXIMUM,
+        }
+    }
+}
+
+impl Needs {
+    // NOTE: Returns the number of needs below `NEGLECTED_TRESHOLD`.
+    pub fn neglected(&self) -> u32 {
+        return [self.hunger, self.rest, self.morale].iter()
+            .filter(|v| **v < NEGLECTED_TRESHOLD)
+            .count() as u32;
+    }
+
+    // NOTE: Returns the chance of a pawn making progress on its work in a turn.
+    pub fn work_speed(&self) -> f32 {
+        return NEGLECTED_WORK_SPEED.powi(self.neglected() as i32);
+    }
+
+    // NOTE: Returns the advantage that should be applied to the pawn's combat rolls.
+    pub fn advantage(&self) -> Advantage {
+        if self.neglected() > 0 {
+            Advantage::Dissadvantage
+        } else {
+            Advantage::Normal
+        }
+    }
+
+    pub fn eat(&mut self) {
+        self.hunger = NEED_MAXIMUM;
+    }
+
+    pub fn sleep(&mut self) {
+        self.rest = (self.rest + SLEEP_RESTORE_RATE).min(NEED_MAXIMUM);
+    }
+
+    pub fn rested(&self) -> bool {
+        return self.rest >= NEED_MAXIMUM;
+    }
+
+    pub fn hungry(&self) -> bool {
+        return self.hunger < HUNGER_TRESHOLD;
+    }
+
+    pub fn tired(&self) -> bool {
+        return self.rest < REST_TRESHOLD;
+    }
+}
+
+// NOTE: Returns whether the pawn is already tasked with satisfying a need.
+fn is_tending_needs(task_queue: &TaskQueue) -> bool {
+    let tending = |t: &Task| matches!(t, Task::Eat(_) | Task::Sleep(_));
+
+    return tending(&task_queue.active) || task_queue.queue.iter().any(tending);
+}
+
+// NOTE: Finds a path to the closest free tile of the given room kind.
+fn find_path_to_room(
+    position: &Position,
+    kind: RoomKind,
+    rooms: &Rooms,
+    alignment: Option<&Alignment>,
+    world: &world::World,
+    factions: &Factions,
+) -> Option<(MoveTask, Position)> {
+    for target in rooms.sorted_by_distance(kind, position) {
+        if target == *position {
+            return Some((MoveTask { path: VecDeque::new(), target }, target));
+        }
+
+        if world.get_entity(target.into()).is_some() {
+            continue;
+        }
+
+        if let Some((mut path, _)) = core::pawn_find_path(*position, target, world, alignment, factions) {
+            path.remove(0);
+
+            return Some((MoveTask { path: VecDeque::from(path), target }, target));
+        }
+    }
+
+    return None;
+}
+
+// NOTE: Decays every pawn's needs at the end of every turn.
+fn decay_needs(
+    mut query: Query<&mut Needs, With<Pawn>>,
+    mut event_reader: EventReader<turn_system::TurnOverEvent>,
+) {
+    let mut over = false;
+    for _ in event_reader.iter() {
+        over = true;
+    }
+
+    if !over {
+        return;
+    }
+
+    for mut needs in &mut query {
+        needs.hunger = (needs.hunger - HUNGER_DECAY_RATE).max(0.0);
+        needs.rest = (needs.rest - REST_DECAY_RATE).max(0.0);
+
+        // NOTE: Morale recovers only if both hunger and rest are met.
+        if needs.hunger < HUNGER_TRESHOLD || needs.rest < REST_TRESHOLD {
+            needs.morale = (needs.morale - MORALE_DECAY_RATE).max(0.0);
+        } else {
+            needs.morale = (needs.morale + MORALE_RECOVERY_RATE).min(NEED_MAXIMUM);
+        }
+    }
+}
+
+// NOTE: Returns the tasks that take the pawn to the closest free tile
+//       of the room kind and satisfy its need there. Pawns that are
+//       already tending their needs are left alone.
+pub fn tend_need(
+    position: &Position,
+    alignment: &Alignment,
+    kind: RoomKind,
+    task_queue: &TaskQueue,
+    rooms: &Rooms,
+    world: &world::World,
+    factions: &Factions,
+) -> Option<Vec<Task>> {
+    if is_tending_needs(task_queue) {
+        return None;
+    }
+
+    let (move_task, target) = find_path_to_room(position, kind, rooms, Some(alignment), world, factions)?;
+
+    info!("Pawn is interrupting its work to visit a {}.", kind.name());
+
+    return Some(vec![
+        Task::Move(move_task),
+        match kind {
+            RoomKind::Stockpile => Task::Eat(target),
+            _ => Task::Sleep(target),
+        },
+    ]);
+}
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+pub mod prelude {
+    pub use super::{
+        SkillKind,
+        Skills,
+    };
+}
+
+// NOTE: Maximum level a skill can reach.
+pub const MAX_SKILL_LEVEL: u32 = 10;
+
+// NOTE: Multiplier for the experience required to reach a level,
+//       reaching level `n` requires `n^2 * SKILL_EXPERIENCE_STEP` experience.
+pub const SKILL_EXPERIENCE_STEP: u32 = 10;
+
+// NOTE: Experience gained for each finished task.
+pub const MINING_EXPERIENCE: u32 = 4;
+pub const BUILDING_EXPERIENCE: u32 = 4;
+pub const MELEE_EXPERIENCE: u32 = 2;
+
+// NOTE: Every skill a pawn can improve by working.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkillKind {
+    Mining,
+    Building,
+    Melee,
+}
+
+impl SkillKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SkillKind::Mining => "Mining",
+            SkillKind::Building => "Building",
+            SkillKind::Melee => "Melee",
+        }
+    }
+}
+
+// NOTE: Per-pawn skills, each skill holds the experience
+//       the pawn has gained so far, levels are derived from it.
+//       - Mining   -> Reduces the time it takes to mine a tile.
+//       - Building -> Reduces the time it takes to build a structure.
+//       - Melee    -> Grants a bonus to melee hit rolls.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Skills {
+    pub mining: u32,
+    pub building: u32,
+    pub melee: u32,
+}
+
+impl Skills {
+    pub const ALL: [SkillKind; 3] = [
+        SkillKind::Mining,
+        SkillKind::Building,
+        SkillKind::Melee,
+    ];
+
+    pub fn experience(&self, kind: SkillKind) -> u32 {
+        match kind {
+            SkillKind::Mining => self.mining,
+            SkillKind::Building => self.building,
+            SkillKind::Melee => self.melee,
+        }
+    }
+
+    // NOTE: Adds experience to the given skill.
+    pub fn gain(&mut self, kind: SkillKind, amount: u32) {
+        let experience = match kind {
+            SkillKind::Mining => &mut self.mining,
+            SkillKind::Building => &mut self.building,
+            SkillKind::Melee => &mut self.melee,
+        };
+
+        *experience += amount;
+    }
+
+    // NOTE: Returns the level of the given skill.
+    pub fn level(&self, kind: SkillKind) -> u32 {
+        let experience = self.experience(kind);
+
+        let mut level = 0;
+        while level < MAX_SKILL_LEVEL && experience >= (level + 1).pow(2) * SKILL_EXPERIENCE_STEP {
+            level += 1;
+        }
+
+        return level;
+    }
+}
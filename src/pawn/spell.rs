@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+use log::info;
+
+use crate::{world, util::prelude::*, turn_system};
+use super::prelude::*;
+
+pub mod prelude {
+    pub use super::{
+        Spell,
+        SpellCooldowns,
+        CastSpellEvent,
+    };
+}
+
+pub struct SpellPlugin;
+
+impl Plugin for SpellPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CastSpellEvent>()
+            .add_system_to_stage(CoreStage::Update, tick_spell_cooldowns)
+            .add_system_to_stage(CoreStage::PostUpdate, resolve_spells);
+    }
+}
+
+// NOTE: Every spell a pawn can cast.
+//       - Fireball      -> Area fire damage, scales with intelligence.
+//       - Heal          -> Heals the target pawn, scales with wisdom.
+//       - LightningBolt -> Single target lightning damage, scales with intelligence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Spell {
+    Fireball,
+    Heal,
+    LightningBolt,
+}
+
+impl Spell {
+    pub const ALL: [Spell; 3] = [
+        Spell::Fireball,
+        Spell::Heal,
+        Spell::LightningBolt,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Spell::Fireball => "Fireball",
+            Spell::Heal => "Heal",
+            Spell::LightningBolt => "Lightning Bolt",
+        }
+    }
+
+    // NOTE: Maximum distance to the target tile in tiles.
+    pub fn range(&self) -> u32 {
+        match self {
+            Spell::Fireball => 7,
+            Spell::Heal => 5,
+            Spell::LightningBolt => 9,
+        }
+    }
+
+    // NOTE: Radius of the affected area in tiles, zero for single target spells.
+    pub fn radius(&self) -> u32 {
+        match self {
+            Spell::Fireball => 2,
+            Spell::Heal => 0,
+            Spell::LightningBolt => 0,
+        }
+    }
+
+    // NOTE: Number of turns that has to pass before the spell can be cast again.
+    pub fn cooldown(&self) -> u32 {
+        match self {
+            Spell::Fireball => 6,
+            Spell::Heal => 4,
+            Spell::LightningBolt => 3,
+        }
+    }
+
+    // NOTE: Dice that are rolled for the spell's damage or healing.
+    pub fn dice(&self) -> (u32, Die) {
+        match self {
+            Spell::Fireball => (2, Die::D6(0)),
+            Spell::Heal => (1, Die::D8(0)),
+            Spell::LightningBolt => (1, Die::D10(0)),
+        }
+    }
+
+    // NOTE: Type of the damage the spell deals, `None` for healing spells.
+    pub fn damage_type(&self) -> Option<DamageType> {
+        match self {
+            Spell::Fireball => Some(DamageType::Fire),
+            Spell::Heal => None,
+            Spell::LightningBolt => Some(DamageType::Lightning),
+        }
+    }
+
+    // NOTE: Status effect the spell applies to its targets and its duration in turns.
+    pub fn effect(&self) -> Option<(StatusKind, u32)> {
+        match self {
+            Spell::Fireball => Some((StatusKind::Burning, 3)),
+            Spell::Heal => Some((StatusKind::Blessed, 3)),
+            Spell::LightningBolt => Some((StatusKind::Stunned, 1)),
+        }
+    }
+
+    // NOTE: Returns the bonus of the stat that the spell scales with,
+    //       wizard spells use intelligence and cleric spells use wisdom.
+    pub fn bonus(&self, stats: &PawnStats) -> i32 {
+        match self {
+            Spell::Fireball | Spell::LightningBolt => get_stat_bonus(stats.intelligence),
+            Spell::Heal => get_stat_bonus(stats.wisdom),
+        }
+    }
+}
+
+// NOTE: Holds the remaining cooldown of every spell the pawn has cast.
+#[derive(Component, Debug, Default)]
+pub struct SpellCooldowns(pub HashMap<Spell, u32>);
+
+impl SpellCooldowns {
+    pub fn ready(&self, spell: Spell) -> bool {
+        return self.0.get(&spell).map_or(true, |c| *c == 0);
+    }
+}
+
+// NOTE: Event that is sent when a pawn casts a spell at a tile.
+#[derive(Clone, Copy)]
+pub struct CastSpellEvent {
+    pub caster: Entity,
+    pub spell: Spell,
+    pub target: Position,
+}
+
+// NOTE: Returns whether the caster is able to cast the spell at the target tile.
+pub fn can_cast(
+    spell: Spell,
+    caster: &Position,
+    target: &Position,
+    world: &world::World,
+) -> bool {
+    return caster.distance(target) <= spell.range().pow(2)
+        && world.has_line_of_sight((*caster).into(), (*target).into());
+}
+
+// NOTE: Reduces every spell cooldown by one at the end of every turn.
+fn tick_spell_cooldowns(
+    mut query: Query<&mut SpellCooldowns>,
+    mut event_reader: EventReader<turn_system::TurnOverEvent>,
+) {
+    let mut over = false;
+    for _ in event_reader.iter() {
+        over = true;
+    }
+
+    if !over {
+        return;
+    }
+
+    for mut cooldowns in &mut query {
+        for cooldown in cooldowns.0.values_mut() {
+            *cooldown = cooldown.saturating_sub(1);
+        }
+    }
+}
+
+// NOTE: Resolves spells, damaging spells hit every pawn in their area and
+//       healing spells heal the pawn on the target tile. Spells always hit,
+//       the damage is reduced by the target's `Resistance` later on, and
+//       every target receives the spell's status effect.
+pub fn resolve_spells(
+    mut casters: Query<(&Position, &PawnStats, &Abilities, &mut SpellCooldowns, Option<&Alignment>), With<Pawn>>,
+    targets: Query<(Entity, &Position), With<Pawn>>,
+    mut event_reader: EventReader<CastSpellEvent>,
+    mut damage_ew: EventWriter<DamageEvent>,
+    mut heal_ew: EventWriter<HealEvent>,
+    mut status_ew: EventWriter<ApplyStatusEvent>,
+    mut rng: ResMut<GameRng>,
+    world: Res<world::World>,
+) {
+    for e in event_reader.iter() {
+        let (position, stats, abilities, mut cooldowns, alignment) = match casters.get_mut(e.caster) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        if !abilities.has(Ability::Cast(e.spell)) || !cooldowns.ready(e.spell) {
+            info!("Pawn is unable to cast {} right now, cast is skipped.", e.spell.name());
+            continue;
+        }
+
+        if !can_cast(e.spell, position, &e.target, &world) {
+            info!("Target of {} is out of reach, cast is skipped.", e.spell.name());
+            continue;
+        }
+
+        cooldowns.0.insert(e.spell, e.spell.cooldown());
+
+        let (count, die) = e.spell.dice();
+        let amount = roll(count, die, e.spell.bonus(stats), Advantage::Normal, rng.stream(RngStream::Spells)).total.max(1);
+
+        let mut affected = vec![];
+
+        match e.spell.damage_type() {
+            Some(damage_type) => {
+                let radius = e.spell.radius().pow(2);
+
+                for (entity, position) in &targets {
+                    if position.distance(&e.target) <= radius {
+                        damage_ew.send(DamageEvent {
+                            target: entity,
+                            amount,
+                            damage_type,
+                            source: alignment.copied(),
+                        });
+
+                        affected.push(entity);
+                    }
+                }
+            },
+            None => {
+                if let Some(target) = world.get_entity(e.target.into()) {
+                    heal_ew.send(HealEvent {
+                        target,
+                        amount,
+                    });
+
+                    affected.push(target);
+                }
+            },
+        }
+
+        if let Some((kind, turns)) = e.spell.effect() {
+            for target in affected {
+                status_ew.send(ApplyStatusEvent {
+                    target,
+                    kind,
+                    turns,
+                });
+            }
+        }
+    }
+}
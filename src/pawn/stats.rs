@@ -1,4 +1,9 @@
 use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::util::{prelude::*, dice};
+use super::initiative;
 
 pub mod prelude {
     pub use super::{
@@ -11,6 +16,9 @@ pub mod prelude {
 // NOTE: Default value for every stat.
 pub const DEFAULT_PAWN_STAT: u32 = 8;
 
+// NOTE: Default speed of a pawn, a pawn with this speed acts once per turn.
+pub const DEFAULT_PAWN_SPEED: u32 = initiative::ACTION_COST;
+
 // NOTE: Collection of base statistics for any given pawn,
 //       the default value of every stat is `DEFAULT_PAWN_STAT`.
 //       - Vitality     -> General durability, and health of a pawn.
@@ -18,13 +26,20 @@ pub const DEFAULT_PAWN_STAT: u32 = 8;
 //       - Dexterity    -> Determines a pawns, ranged damage and dodge stats.
 //       - Intelligence -> Determines the wizard spell damage, and effectivenes.
 //       - Wisdom       -> Determines the cleric spell damage, and effectivenes.
-#[derive(Component)]
+//       - Speed        -> Determines how often a pawn acts, it isn't rolled.
+#[derive(Component, Debug, Clone, Copy, Deserialize)]
 pub struct PawnStats {
     pub vitality: u32,
     pub strenght: u32,
     pub dexterity: u32,
     pub intelligence: u32,
     pub wisdom: u32,
+    #[serde(default = "default_speed")]
+    pub speed: u32,
+}
+
+fn default_speed() -> u32 {
+    return DEFAULT_PAWN_SPEED;
 }
 
 impl Default for PawnStats {
@@ -35,10 +50,35 @@ impl Default for PawnStats {
             dexterity: DEFAULT_PAWN_STAT,
             intelligence: DEFAULT_PAWN_STAT,
             wisdom: DEFAULT_PAWN_STAT,
+            speed: DEFAULT_PAWN_SPEED,
         };
     }
 }
 
+impl PawnStats {
+    // NOTE: Rolls every stat by rolling 4d6 and dropping the lowest die.
+    pub fn roll<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        return Self {
+            vitality: roll_stat(rng),
+            strenght: roll_stat(rng),
+            dexterity: roll_stat(rng),
+            intelligence: roll_stat(rng),
+            wisdom: roll_stat(rng),
+            speed: DEFAULT_PAWN_SPEED,
+        };
+    }
+}
+
+// NOTE: Dice expression that is rolled for every stat.
+const STAT_ROLL: &str = "4d6dl1";
+
+// NOTE: Rolls a single stat, 4d6 with the lowest die dropped.
+fn roll_stat<R: Rng + ?Sized>(rng: &mut R) -> u32 {
+    let result = dice::expression(STAT_ROLL).roll(Advantage::Normal, rng);
+
+    return result.total as u32;
+}
+
 // NOTE: Returns the stat bonus for any given stat.
 pub fn get_stat_bonus(value: u32) -> i32 {
     return value as i32 - DEFAULT_PAWN_STAT as i32;
@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{world::fluid, util::prelude::*, turn_system};
+use super::{prelude::*, core};
+
+pub mod prelude {
+    pub use super::{
+        StatusKind,
+        StatusEffects,
+        OnHitEffect,
+        ApplyStatusEvent,
+    };
+}
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyStatusEvent>()
+            .add_system_to_stage(
+                CoreStage::Update,
+                tick_status_effects.after(core::process_pawn_turns).after(fluid::apply_fluid_effects)
+            )
+            .add_system_to_stage(CoreStage::PostUpdate, apply_status_effects);
+    }
+}
+
+// NOTE: Every status effect a pawn can suffer from or benefit of.
+//       - Burning  -> Takes fire damage every turn.
+//       - Poisoned -> Takes poison damage every turn, rolls with dissadvantage.
+//       - Stunned  -> Skips its turns.
+//       - Slowed   -> Acts only every other turn, rolls with dissadvantage.
+//       - Blessed  -> Rolls with advantage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum StatusKind {
+    Burning,
+    Poisoned,
+    Stunned,
+    Slowed,
+    Blessed,
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Burning => "Burning",
+            StatusKind::Poisoned => "Poisoned",
+            StatusKind::Stunned => "Stunned",
+            StatusKind::Slowed => "Slowed",
+            StatusKind::Blessed => "Blessed",
+        }
+    }
+
+    // NOTE: Dice and type of the damage that is dealt every turn, if there is any.
+    pub fn damage(&self) -> Option<(u32, Die, DamageType)> {
+        match self {
+            StatusKind::Burning => Some((1, Die::D4(0), DamageType::Fire)),
+            StatusKind::Poisoned => Some((1, Die::D4(0), DamageType::Poison)),
+            _ => None,
+        }
+    }
+
+    // NOTE: How the effect changes the dice rolls of the pawn.
+    pub fn advantage(&self) -> Advantage {
+        match self {
+            StatusKind::Poisoned | StatusKind::Slowed => Advantage::Dissadvantage,
+            StatusKind::Blessed => Advantage::Advantage,
+            _ => Advantage::Normal,
+        }
+    }
+}
+
+// NOTE: A single effect and the number of turns it lasts.
+#[derive(Debug, Clone, Copy)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns: u32,
+}
+
+// NOTE: Component that holds the active status effects of a pawn.
+#[derive(Component, Debug, Clone, Default)]
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        return self.0.iter().any(|e| e.kind == kind);
+    }
+
+    // NOTE: Applies the effect, effects don't stack and reapplying
+    //       an active effect only refreshes its duration.
+    pub fn apply(&mut self, kind: StatusKind, turns: u32) {
+        match self.0.iter_mut().find(|e| e.kind == kind) {
+            Some(e) => e.turns = e.turns.max(turns),
+            None => self.0.push(StatusEffect { kind, turns }),
+        }
+    }
+
+    // NOTE: Returns whether the pawn loses its turn, stunned pawns
+    //       never act and slowed pawns act only every other turn.
+    pub fn skips_turn(&self) -> bool {
+        if self.has(StatusKind::Stunned) {
+            return true;
+        }
+
+        return self.0.iter().any(|e| e.kind == StatusKind::Slowed && e.turns % 2 == 1);
+    }
+
+    // NOTE: Returns the advantage of every active effect, these
+    //       are resolved together with the other sources of a roll.
+    pub fn advantages(&self) -> Vec<Advantage> {
+        return self.0.iter().map(|e| e.kind.advantage()).collect();
+    }
+}
+
+// NOTE: Component for pawns whose weapon attacks apply a
+//       status effect on hit, and the duration of the effect.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct OnHitEffect(pub StatusKind, pub u32);
+
+// NOTE: Event that is sent to apply a status effect to a pawn.
+#[derive(Clone, Copy)]
+pub struct ApplyStatusEvent {
+    pub target: Entity,
+    pub kind: StatusKind,
+    pub turns: u32,
+}
+
+// NOTE: Applies status effects to pawns, pawns that are immune to
+//       the damage type of an effect are also immune to the effect.
+fn apply_status_effects(
+    mut query: Query<(&mut StatusEffects, Option<&Resistance>), With<Pawn>>,
+    mut event_reader: EventReader<ApplyStatusEvent>,
+) {
+    for e in event_reader.iter() {
+        let (mut effects, resistance) = match query.get_mut(e.target) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        let immune = match (e.kind.damage(), resistance) {
+            (Some((_, _, damage_type)), Some(r)) => r.immunities.contains(&damage_type),
+            _ => false,
+        };
+
+        if !immune {
+            effects.apply(e.kind, e.turns);
+        }
+    }
+}
+
+// NOTE: Ticks the status effects at the end of every turn, in the order
+//       of the entities. Damaging effects deal their damage and expired
+//       effects are removed. Effects are ticked after the pawns acted,
+//       so that an effect that lasts a turn is in effect for that turn.
+fn tick_status_effects(
+    mut query: Query<(Entity, &mut StatusEffects), With<Pawn>>,
+    mut event_reader: EventReader<turn_system::TurnOverEvent>,
+    mut damage_ew: EventWriter<DamageEvent>,
+    mut rng: ResMut<GameRng>,
+) {
+    let mut over = false;
+    for _ in event_reader.iter() {
+        over = true;
+    }
+
+    if !over {
+        return;
+    }
+
+    let mut entities: Vec<Entity> = query.iter().map(|q| q.0).collect();
+    entities.sort();
+
+    for entity in entities {
+        let (_, mut effects) = match query.get_mut(entity) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        if effects.0.is_empty() {
+            continue;
+        }
+
+        for effect in effects.0.iter_mut() {
+            if let Some((count, die, damage_type)) = effect.kind.damage() {
+                damage_ew.send(DamageEvent {
+                    target: entity,
+                    amount: roll(count, die, 0, Advantage::Normal, rng.stream(RngStream::Combat)).total.max(1),
+                    damage_type,
+                    source: None,
+                });
+            }
+
+            effect.turns = effect.turns.saturating_sub(1);
+        }
+
+        effects.0.retain(|e| e.turns > 0);
+    }
+}
@@ -1,18 +1,39 @@
 use bevy::prelude::*;
+use rand::{Rng, rngs::StdRng};
 use std::collections::VecDeque;
 use log::{info, error};
 
-use crate::world;
-use super::{core::{prelude::*, self}, worker};
+use crate::{world::{self, structure::{self, Structure, DoorAccess}}, util::prelude::*};
+use super::{
+    core::{prelude::*, self},
+    worker,
+    needs::Needs,
+    faction::prelude::*,
+    combat::prelude::*,
+    spell::prelude::*,
+    status::prelude::*,
+    skills::{self, prelude::*},
+};
 
 pub mod prelude {
     pub use super::{
         Task,
         TaskQueue,
         MoveTask,
+        MineTask,
+        BuildTask,
     };
 }
 
+// NOTE: Minimum number of turns it takes to mine a tile, regardless of skill.
+pub const MINIMUM_MINING_TIME: u32 = 1;
+
+// NOTE: Number of turns it takes an unskilled pawn to build a pillar.
+pub const BUILDING_TIME: u32 = 4;
+
+// NOTE: Minimum number of turns it takes to build a pillar, regardless of skill.
+pub const MINIMUM_BUILDING_TIME: u32 = 1;
+
 // NOTE: Holds the path and location of the target tile.
 #[derive(Debug, Clone)]
 pub struct MoveTask {
@@ -20,6 +41,46 @@ pub struct MoveTask {
     pub target: Position,
 }
 
+// NOTE: Holds the target tile and work id of a mine task, and
+//       how many turns the pawn has spent mining the tile so far.
+#[derive(Debug, Clone)]
+pub struct MineTask {
+    pub target: Position,
+    pub id: worker::GlobalWorkID,
+    pub progress: u32,
+}
+
+impl MineTask {
+    pub fn new(target: Position, id: worker::GlobalWorkID) -> Self {
+        Self {
+            target,
+            id,
+            progress: 0,
+        }
+    }
+}
+
+// NOTE: Holds the target tile, structure and work id of a build
+//       task, and how many turns the pawn has spent building so far.
+#[derive(Debug, Clone)]
+pub struct BuildTask {
+    pub target: Position,
+    pub structure: Structure,
+    pub id: worker::GlobalWorkID,
+    pub progress: u32,
+}
+
+impl BuildTask {
+    pub fn new(target: Position, structure: Structure, id: worker::GlobalWorkID) -> Self {
+        Self {
+            target,
+            structure,
+            id,
+            progress: 0,
+        }
+    }
+}
+
 // NOTE: A task is basically what a pawn is going to do that turn.
 //       This enum holds the every possible task for a pawn.
 #[allow(dead_code)]
@@ -28,7 +89,12 @@ pub enum Task {
     None,
     Move(MoveTask),
     Attack(Entity),
-    Mine((Position, worker::GlobalWorkID)),
+    Shoot(Entity),
+    Cast((Spell, Position)),
+    Mine(MineTask),
+    Build(BuildTask),
+    Eat(Position),
+    Sleep(Position),
 }
 
 #[allow(dead_code)]
@@ -38,7 +104,12 @@ impl Task {
             Task::None => "None",
             Task::Move(_) => "Move",
             Task::Attack(_) => "Attack",
+            Task::Shoot(_) => "Shoot",
+            Task::Cast(_) => "Cast",
             Task::Mine(_) => "Mine",
+            Task::Build(_) => "Build",
+            Task::Eat(_) => "Eat",
+            Task::Sleep(_) => "Sleep",
         }.to_string()
     } 
 }
@@ -63,6 +134,47 @@ impl TaskQueue {
     }
 }
 
+// NOTE: Damages the door on the given tile if the pawn has to break it down
+//       to get through, returns whether the pawn spent its turn on the door.
+//       Broken doors are removed and the tile can be walked on again.
+fn break_door(
+    target: (usize, usize, usize),
+    world: &mut world::World,
+    alignment: Option<&Alignment>,
+    factions: &Factions,
+    rng: &mut StdRng,
+) -> bool {
+    if !world.in_bounds(target) || world.get_entity(target).is_some() {
+        return false;
+    }
+
+    match world.get_tile(target).door {
+        Some(door) if world.door_access(&door, alignment, factions) == DoorAccess::Break => {},
+        _ => return false,
+    }
+
+    let (count, die) = structure::DOOR_BREAK_DAMAGE;
+    let amount = roll(count, die, 0, Advantage::Normal, rng).total.max(1);
+
+    let tile = world.get_tile_mut(target);
+
+    if let Some(door) = &mut tile.door {
+        if door.damage(amount) {
+            tile.door = None;
+
+            info!("The door at {:?} was broken down.", target);
+        }
+    }
+
+    return true;
+}
+
+// NOTE: Pawns with neglected needs work slower, they may
+//       lose their turn without making any progress.
+fn loses_work_turn(needs: Option<&Needs>, rng: &mut StdRng) -> bool {
+    return needs.map_or(false, |n| !rng.gen_bool(n.work_speed() as f64));
+}
+
 // NOTE: Determines the turn logic for every pawn.
 pub fn pawn_act_turn(
     entity: Entity,
@@ -70,9 +182,26 @@ pub fn pawn_act_turn(
     transform: &mut Transform,
     position: &mut Position,
     world: &mut world::World,
+    needs: Option<&mut Needs>,
+    skills: Option<&mut Skills>,
+    effects: Option<&StatusEffects>,
+    alignment: Option<&Alignment>,
+    factions: &Factions,
+    rng: &mut StdRng,
     gw_validator: &mut worker::GlobalWorkValidator,
     mine_tile_er: &mut EventWriter<worker::MineTileEvent>,
+    build_tile_ew: &mut EventWriter<worker::BuildTileEvent>,
+    attack_ew: &mut EventWriter<AttackEvent>,
+    cast_ew: &mut EventWriter<CastSpellEvent>,
 ) {
+    // NOTE: Stunned and slowed pawns may lose their turn,
+    //       the active task is kept until they can act again.
+    if let Some(effects) = effects {
+        if effects.skips_turn() {
+            return;
+        }
+    }
+
     match &mut task_queue.active {
         Task::None => {}
         Task::Move(move_task) => {
@@ -82,8 +211,18 @@ pub fn pawn_act_turn(
                     entity,
                     transform, 
                     position, 
-                    world
+                    world,
+                    alignment,
+                    factions,
                 );
+
+                // NOTE: Pawns that can't open the door in their way break it
+                //       down, they keep the move task until the door breaks.
+                if result == false && break_door(target.into(), world, alignment, factions, rng) {
+                    move_task.path.push_front(target);
+
+                    return;
+                }
     
                 // NOTE: If next tile on the path is invalid, try to find a new path.
                 if result == false {
@@ -92,7 +231,9 @@ pub fn pawn_act_turn(
                     let result = core::pawn_find_path(
                         *position, 
                         move_task.target, 
-                        world
+                        world,
+                        alignment,
+                        factions,
                     );
 
                     match result {
@@ -119,25 +260,58 @@ pub fn pawn_act_turn(
                 }
             }
         }
-        Task::Attack(_) => {},
-        Task::Mine((target, id)) => {
+        Task::Attack(target) => {
+            attack_ew.send(AttackEvent {
+                attacker: entity,
+                target: *target,
+                kind: AttackKind::Melee,
+            });
+
+            if let Some(skills) = skills {
+                skills.gain(SkillKind::Melee, skills::MELEE_EXPERIENCE);
+            }
+        },
+        Task::Shoot(target) => {
+            attack_ew.send(AttackEvent {
+                attacker: entity,
+                target: *target,
+                kind: AttackKind::Ranged,
+            });
+        },
+        Task::Cast((spell, target)) => {
+            cast_ew.send(CastSpellEvent {
+                caster: entity,
+                spell: *spell,
+                target: *target,
+            });
+        },
+        Task::Mine(MineTask { target, id, progress }) => {
             // NOTE: Get the current work from the pool
             let result = gw_validator.validate(id);
 
             if let Some(_) = result {
-                // NOTE: Calculate the distance to the target tile.
-                let dist = Vec2::new(
-                    (target.x - position.x).abs() as f32,
-                    (target.y - position.y).abs() as f32,
-                ).length();
-
                 // NOTE: If the pawn failed to reach to the target tile
                 //       for some reason, set work to unoccupied again.
-                if dist > f32::sqrt(2.0) {
+                if position.distance(target) > 2 || position.z != target.z {
                     gw_validator.set_occupied(id, false);
 
                     info!("Failed to reach to the current work, mine task is skipped.");
                 } else {
+                    // NOTE: Keep mining until enough turns are spent on the tile,
+                    //       harder materials take longer and mining skill speeds it up.
+                    let level = skills.as_ref().map_or(0, |s| s.level(SkillKind::Mining));
+                    let hardness = world.get_tile((*target).into()).resource.material.hardness();
+
+                    if loses_work_turn(needs.as_deref(), rng) {
+                        return;
+                    }
+
+                    *progress += 1;
+
+                    if *progress < hardness.saturating_sub(level / 2).max(MINIMUM_MINING_TIME) {
+                        return;
+                    }
+
                     // NOTE: Otherwise remove the work from the `GlobalWorkValidator`.
                     let result = gw_validator.remove_work(id);
 
@@ -148,11 +322,76 @@ pub fn pawn_act_turn(
 
                     // NOTE: Send a `MineTileEvent` with given target position.
                     mine_tile_er.send(worker::MineTileEvent(*target));
+
+                    if let Some(skills) = skills {
+                        skills.gain(SkillKind::Mining, skills::MINING_EXPERIENCE);
+                    }
                 }
             } else {
                 info!("Failed to validate work from the `GlobalWorkValidator`, mine task is skipped.");
             }
         },
+        Task::Build(BuildTask { target, structure, id, progress }) => {
+            if gw_validator.validate(id).is_none() {
+                info!("Failed to validate work from the `GlobalWorkValidator`, build task is skipped.");
+            } else if position.distance(target) > 2 || position.z != target.z {
+                gw_validator.set_occupied(id, false);
+
+                info!("Failed to reach to the current work, build task is skipped.");
+            } else {
+                // NOTE: Wait until the tile is free, structures
+                //       can't be built on top of other pawns.
+                if world.get_entity((*target).into()).is_some() {
+                    return;
+                }
+
+                let level = skills.as_ref().map_or(0, |s| s.level(SkillKind::Building));
+
+                if loses_work_turn(needs.as_deref(), rng) {
+                    return;
+                }
+
+                *progress += 1;
+
+                if *progress < BUILDING_TIME.saturating_sub(level / 2).max(MINIMUM_BUILDING_TIME) {
+                    return;
+                }
+
+                if gw_validator.remove_work(id).is_none() {
+                    error!("Failed to remove work from the `GlobalWorkValidator`, this should have never happened.");
+                    panic!();
+                }
+
+                build_tile_ew.send(worker::BuildTileEvent(*target, *structure));
+
+                if let Some(skills) = skills {
+                    skills.gain(SkillKind::Building, skills::BUILDING_EXPERIENCE);
+                }
+            }
+        },
+        Task::Eat(target) => {
+            if *position == *target {
+                if let Some(needs) = needs {
+                    needs.eat();
+                }
+            } else {
+                info!("Failed to reach to the stockpile, eat task is skipped.");
+            }
+        },
+        Task::Sleep(target) => {
+            if *position == *target {
+                if let Some(needs) = needs {
+                    needs.sleep();
+
+                    // NOTE: Keep the pawn sleeping until it's fully rested.
+                    if !needs.rested() {
+                        return;
+                    }
+                }
+            } else {
+                info!("Failed to reach to the dormitory, sleep task is skipped.");
+            }
+        },
     }
 
     task_queue.next_tast();
@@ -2,13 +2,14 @@ use std::collections::{VecDeque, HashMap};
 use bevy::prelude::*;
 
 use crate::{
-    world::{self, tile}, 
-    tileset, 
+    world::{self, tile, structure::{Structure, Door, Trap}},
     player::{resource, order},
 };
 use super::{
     turn::prelude::*,
-    core::{prelude::*, self, spawn_default_pawn_with_alignment},
+    core::{prelude::*, self},
+    archetype::prelude::*,
+    faction::prelude::*,
 };
 
 pub struct WorkerPlugin;
@@ -16,20 +17,18 @@ pub struct WorkerPlugin;
 impl Plugin for WorkerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<MineTileEvent>()
+            .add_event::<BuildTileEvent>()
             .add_event::<RemoveGlobalWorkEvent>()
             .add_event::<RegisterGlobalWorkEvent>()
             .add_startup_system_to_stage(StartupStage::PostStartup, setup_global_work_pool)
-            .add_system_to_stage(CoreStage::PreUpdate, worker_behaviour)
             .add_system_to_stage(CoreStage::PreUpdate, check_inaccessible_works)
             .add_system_to_stage(CoreStage::PostUpdate, register_global_work_event)
             .add_system_to_stage(CoreStage::PostUpdate, remove_global_work_event)
-            .add_system_to_stage(CoreStage::PostUpdate, mine_tile_event);
+            .add_system_to_stage(CoreStage::PostUpdate, mine_tile_event)
+            .add_system_to_stage(CoreStage::PostUpdate, build_tile_event);
     }
 }
 
-// NOTE: Default glyph that is used for worker pawns.
-pub const DEFAULT_WORKER_PAWN_GLYPH: usize = 1;
-
 // NOTE: Amount of works to be rechecked for every worker in a frame.
 pub const MAX_WORK_RECHECK_COUNT: usize = 20;
 
@@ -38,20 +37,22 @@ pub const MAX_ACCESSIBLE_WORK_TRESHOLD: usize = 300;
 
 // NOTE: Work identifiers
 pub const MINE_WORK_IDENTIFIER: &str = "m";
+pub const BUILD_WORK_IDENTIFIER: &str = "b";
 
-// NOTE: Identifier used to distinguish between works, first letter
-//       is a code for the type and the numbers are the position.
+// NOTE: Identifier used to distinguish between works, made of a code
+//       for the type of the work and the position of its tile, so works
+//       on the same tile of different levels don't share an identifier.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct GlobalWorkID {
-    id: String,
+    kind: &'static str,
+    position: (i32, i32, i32),
 }
 
 impl GlobalWorkID {
-    pub fn new(work_identifier: &str, position: &Position) -> Self {
+    pub fn new(work_identifier: &'static str, position: &Position) -> Self {
         Self {
-            id: work_identifier.to_string() + 
-                &position.x.to_string() + 
-                &position.y.to_string(),
+            kind: work_identifier,
+            position: (position.x, position.y, position.z),
         }
     }
 }
@@ -119,6 +120,16 @@ pub struct Worker {
     iterator: usize,
 }
 
+impl Default for Worker {
+    fn default() -> Self {
+        Self {
+            accessible: Vec::with_capacity(500),
+            inaccessible: Vec::with_capacity(500),
+            iterator: 0,
+        }
+    }
+}
+
 // NOTE: Event that is used to send a global work to worker pawns.
 pub struct RegisterGlobalWorkEvent {
     work: GlobalWork,
@@ -149,30 +160,28 @@ impl RemoveGlobalWorkEvent {
 #[derive(Clone, Copy)]
 pub struct MineTileEvent(pub Position);
 
-// NOTE: Spawns a worker pawn, every worker
-//       pawn is currently owned by the player.
-pub fn spawn_worker_pawn(
-    commands: &mut Commands,
-    world: &mut world::World,
-    tileset: &tileset::Tileset,
-    position: (usize, usize),
-) -> Entity {
-    let e = spawn_default_pawn_with_alignment(
-        commands, 
-        world, 
-        tileset, 
-        DEFAULT_WORKER_PAWN_GLYPH, 
-        position, 
-        Alignment::Player
-    );
-
-    commands.entity(e).insert(Worker {
-        accessible: Vec::with_capacity(500),
-        inaccessible: Vec::with_capacity(500),
-        iterator: 0,
-    });
+// NOTE: Event that is sent when a worker builds a structure on a tile.
+#[derive(Clone, Copy)]
+pub struct BuildTileEvent(pub Position, pub Structure);
 
-    return e;
+// NOTE: Clears the active task and the task queue of a pawn, every
+//       mine and build work in them is set to unoccupied again.
+pub fn release_task_queue(
+    task_queue: &mut TaskQueue,
+    gw_validator: &mut GlobalWorkValidator,
+) {
+    for task in std::iter::once(&task_queue.active).chain(task_queue.queue.iter()) {
+        let id = match task {
+            Task::Mine(mine_task) => &mine_task.id,
+            Task::Build(build_task) => &build_task.id,
+            _ => continue,
+        };
+
+        gw_validator.set_occupied(id, false);
+    }
+
+    task_queue.queue.clear();
+    task_queue.active = Task::None;
 }
 
 // NOTE: Setup the `GlobalWorkPool` resource.
@@ -180,67 +189,43 @@ fn setup_global_work_pool(mut commands: Commands) {
     commands.insert_resource(GlobalWorkValidator::default());
 }
 
-// NOTE: Find the best position around the target
-// TODO: Optimisse this algorithm, to find better paths with higher efficiency.
-fn find_best_path_to_target(
+// NOTE: Find the cheapest path to a free tile around the target, with a
+//       single search that succeeds on any of them. The heuristic to the
+//       target is lowered by a diagonal move, so it's still a lower bound
+//       of the cost to the closest tile around it.
+pub fn find_best_path_to_target(
     position: &Position,
     target: &Position,
     world: &world::World,
+    alignment: Option<&Alignment>,
+    factions: &Factions,
 ) -> Option<MoveTask> {
-    let mut best_cost = u32::MAX;
-
-    let mut best_path = vec![];
-    let mut best_pos = Position::new(-1, -1);
-
-    for y in -1..=1_i32 {
-        for x in -1..=1_i32 {
-            if x == 0 && y == 0 {
-                continue;
-            }
-
-            let target = Position::new(
-                target.x + x,
-                target.y + y,
-            );
-
-            // NOTE: If that tile is a solid one or if it is already
-            //       occupied don't bother trying to find a path.
-            if world.is_solid_tile(target.into()) || 
-                world.get_entity(target.into()).is_some() {
-                continue;
-            }
-
-            let result = core::pawn_find_path(
-                *position, 
-                target, 
-                world
-            );
-
-            match result {
-                Some((path, cost)) => {
-                    if cost < best_cost {
-                        best_cost = cost;
-
-                        best_path = path;
-                        best_pos = target;
-                    }
-                },
-                None => {}
-            }
-        }
-    }
-
-    if best_path.is_empty() {
-        return None;
-    } else {
-        // NOTE: Remove the initial position from the path.
-        best_path.remove(0);
-
-        return Some(MoveTask {
-            path: VecDeque::from(best_path),
-            target: best_pos
-        })
-    }
+    let around = |p: &Position| {
+        p.z == target.z
+            && p != target
+            && (p.x - target.x).abs() <= 1
+            && (p.y - target.y).abs() <= 1
+            && world.get_entity((*p).into()).is_none()
+    };
+
+    let (mut path, _) = core::find_path_bounded(
+        *position,
+        |p| p.path_heuristic(target).saturating_sub(core::MOVEMENT_COST_DIAGONAL),
+        around,
+        world,
+        alignment,
+        factions,
+    )?;
+
+    let best_pos = *path.last().unwrap();
+
+    // NOTE: Remove the initial position from the path.
+    path.remove(0);
+
+    return Some(MoveTask {
+        path: VecDeque::from(path),
+        target: best_pos,
+    });
 }
 
 // NOTE: Returns the distance between a pawn an a global task.
@@ -254,99 +239,104 @@ fn distance_to_work(
     ).length();
 }
 
-// NOTE: Behaviour code which determines what workers do under certain circumstances.
-fn worker_behaviour(
-    mut query: Query<(&Position, &mut TaskQueue, &mut Worker)>,
-    mut gw_validator: ResMut<GlobalWorkValidator>,
-    world: Res<world::World>,
-) {
-    for (position, mut tq, mut worker) in &mut query {
-        let active = if let Task::None = tq.active { 
-            false
-        } else { 
-            true
-        };
+// NOTE: Picks the nearest unoccupied work the worker is able to do, and
+//       returns the tasks to reach and do it. The work is marked as occupied,
+//       works without a path are moved to the inaccessible list.
+pub fn take_work(
+    position: &Position,
+    worker: &mut Worker,
+    abilities: Option<&Abilities>,
+    alignment: Option<&Alignment>,
+    gw_validator: &mut GlobalWorkValidator,
+    world: &world::World,
+    factions: &Factions,
+) -> Option<Vec<Task>> {
+    // NOTE: Only workers which are able to mine can take mine works.
+    let can_mine = abilities.map_or(true, |a| a.has(Ability::Mine));
 
-        if active || !tq.queue.is_empty() {
+    // NOTE: Find the nearest unoccupied task.
+    let mut index = -1;
+    let mut close = f32::MAX;
+
+    // NOTE: Exhausted works
+    let mut exhausted = vec![];
+
+    for (i, work) in worker.accessible.iter().enumerate() {
+        if !can_mine && matches!(work.task, Task::Mine(_)) {
             continue;
         }
 
-        // NOTE: Find the nearest unoccupied task.
-        let mut index = -1;
-        let mut close = f32::MAX;
-
-        // NOTE: Exhausted works
-        let mut exhausted = vec![];
-
-        for (i, work) in worker.accessible.iter().enumerate() {
-            // NOTE: Validate the work.
-            match gw_validator.validate(&work.id) {
-                Some(occupied) => {
-                    // NOTE: Skip the work if it's alrady occupied.
-                    if *occupied {
-                        continue;
-                    }
-                },
-                // NOTE: Handle the case of work no longer existing.
-                None => {
-                    exhausted.push(work.id.clone());
+        // NOTE: Validate the work.
+        match gw_validator.validate(&work.id) {
+            Some(occupied) => {
+                // NOTE: Skip the work if it's alrady occupied.
+                if *occupied {
                     continue;
                 }
+            },
+            // NOTE: Handle the case of work no longer existing.
+            None => {
+                exhausted.push(work.id.clone());
+                continue;
             }
+        }
 
-            // NOTE: Find the distance to the work.
-            let dist = distance_to_work(position, &work);
+        // NOTE: Find the distance to the work.
+        let dist = distance_to_work(position, &work);
 
-            if dist < close {
-                close = dist;
-                index = i as i32;
-            }
+        if dist < close {
+            close = dist;
+            index = i as i32;
         }
+    }
 
-        // NOTE: If a work is available task it.
-        if index != -1 {
-            let work = worker.accessible.get_mut(index as usize).unwrap();
+    let mut result = None;
 
-            // NOTE: Find the best path to nearest position around work.
-            let result = find_best_path_to_target(position, &work.position, &world);
+    // NOTE: If a work is available task it.
+    if index != -1 {
+        let work = worker.accessible.get_mut(index as usize).unwrap();
 
-            // NOTE: Send the required taks to worker.
-            if let Some(mt) = result {
-                tq.queue.push_back(Task::Move(mt));
-                tq.queue.push_back(work.task.clone());
+        // NOTE: Find the best path to nearest position around work.
+        let path = find_best_path_to_target(position, &work.position, world, alignment, factions);
 
-                // NOTE: Mark the work as occupied.
-                gw_validator.set_occupied(&work.id, true);
-            } else {
-                // NOTE: Add work to the exhausted list to be removed from the accessible list.
-                exhausted.push(work.id.clone());
+        // NOTE: Send the required taks to worker.
+        if let Some(mt) = path {
+            result = Some(vec![Task::Move(mt), work.task.clone()]);
 
-                // NOTE: If no path is available, push the work to the inaccessible list.
-                let clone = work.clone();
-                worker.inaccessible.push(clone);
-            }
+            // NOTE: Mark the work as occupied.
+            gw_validator.set_occupied(&work.id, true);
+        } else {
+            // NOTE: Add work to the exhausted list to be removed from the accessible list.
+            exhausted.push(work.id.clone());
+
+            // NOTE: If no path is available, push the work to the inaccessible list.
+            let clone = work.clone();
+            worker.inaccessible.push(clone);
         }
+    }
 
-        // NOTE: Remove exhausted works from the work list
-        let mut iter = 0;
-        while iter < worker.accessible.len() {
-            if exhausted.contains(&worker.accessible[iter].id) {
-                worker.accessible.remove(iter);
-            } else {
-                iter += 1;
-            }
+    // NOTE: Remove exhausted works from the work list
+    let mut iter = 0;
+    while iter < worker.accessible.len() {
+        if exhausted.contains(&worker.accessible[iter].id) {
+            worker.accessible.remove(iter);
+        } else {
+            iter += 1;
         }
     }
+
+    return result;
 }
 
 // NOTE: Checks every workers inaccessible works and
 //       promotes them to accessible ones.
 fn check_inaccessible_works(
-    mut query: Query<(&Position, &mut Worker)>,
+    mut query: Query<(&Position, &mut Worker, Option<&Alignment>)>,
     mut gw_validator: ResMut<GlobalWorkValidator>,
     world: Res<world::World>,
+    factions: Res<Factions>,
 ) {
-    for (position, mut worker) in &mut query {
+    for (position, mut worker, alignment) in &mut query {
         if worker.accessible.len() > MAX_ACCESSIBLE_WORK_TRESHOLD {
             continue;
         }
@@ -368,7 +358,7 @@ fn check_inaccessible_works(
                 invalid.push(work.id.clone());
             } else {
                 let result = find_best_path_to_target(
-                    position, &work.position, &world
+                    position, &work.position, &world, alignment, &factions
                 );
         
                 match result {
@@ -419,15 +409,16 @@ fn check_inaccessible_works(
 // TODO: Send works to `GlobalWorkValidator` in batches
 //       instead sending all at once.
 fn register_global_work_event(
-    mut query: Query<(&Position, &mut Worker)>,
+    mut query: Query<(&Position, &mut Worker, Option<&Alignment>)>,
     mut gw_validator: ResMut<GlobalWorkValidator>,
     mut event_reader: EventReader<RegisterGlobalWorkEvent>,
     world: Res<world::World>,
+    factions: Res<Factions>,
 ) {
     for e in event_reader.iter() {
-        for (position, mut worker) in &mut query {
+        for (position, mut worker, alignment) in &mut query {
             let result = find_best_path_to_target(
-                position, &e.work.position, &world
+                position, &e.work.position, &world, alignment, &factions
             );
 
             match result {
@@ -456,12 +447,11 @@ fn remove_global_work_event(
 }
 
 // NOTE: Event that is used to mine a tile, apply
-//       required changes to world and tile entity.
-fn mine_tile_event(
+//       required changes to world and indicator entity.
+pub fn mine_tile_event(
     mut commands: Commands,
     mut world: ResMut<world::World>,
     mut event_reader: EventReader<MineTileEvent>,
-    mut tiles: Query<(&Position, &mut tile::Tile)>,
     mut player_resources: ResMut<resource::PlayerResources>,
     indicators: Query<(Entity, &Position), With<order::MineOrderIndicator>>,
 ) {
@@ -487,10 +477,10 @@ fn mine_tile_event(
         // NOTE: Increase the player's resource count acording to the tile's material
         let mut res =  &mut player_resources.resources[tile.resource.material as usize];
 
-        res.quantity += 1;
+        res.quantity += tile.resource.quantity;
 
         // NOTE: Change the grid data for the target tile
-        world.grid.remove_vertex(target.into());
+        world.remove_solid_tile(target.into());
     }
 
     // NOTE: Despawn the indicator entities with target positions.
@@ -499,25 +489,50 @@ fn mine_tile_event(
             commands.entity(entity).despawn();
         }
     }
+}
 
-    // NOTE: Change tiles with the same positions in the array.
-    for (position, mut tile) in &mut tiles {
-        let mut index = -1;
-        for (i, target) in targets.iter().enumerate() {
-            if position == target {
-                index = i as i32;
-                break;
-            }
-        }
+// NOTE: Builds the structures of the finished build works on their target
+//       tiles, and despawns their indicators. Materials are paid when ordered.
+fn build_tile_event(
+    mut commands: Commands,
+    mut world: ResMut<world::World>,
+    mut event_reader: EventReader<BuildTileEvent>,
+    indicators: Query<(Entity, &Position), With<order::BuildOrderIndicator>>,
+) {
+    if event_reader.is_empty() {
+        return;
+    }
 
-        if index == -1 {
-            continue;
+    let mut targets = vec![];
+
+    for e in event_reader.iter() {
+        targets.push(e.0);
+
+        let tile = world.get_tile_mut(e.0.into());
+
+        match e.1 {
+            Structure::Pillar => {
+                tile.state = tile::TileState::Pillar;
+                tile.fluid = None;
+            },
+            Structure::Door => {
+                tile.door = Some(Door::default());
+            },
+            Structure::Trap(kind) => {
+                tile.trap = Some(Trap::new(kind));
+            },
         }
 
-        // NOTE: Remove target position from the vector,
-        //       to decrease total number of iterations.
-        targets.remove(index as usize);
+        tile.marked = false;
 
-        tile.state = tile::TileState::Empty;
+        if let Structure::Trap(_) = e.1 {
+            world.traps.insert(e.0.into());
+        }
+    }
+
+    for (entity, position) in &indicators {
+        if targets.contains(position) {
+            commands.entity(entity).despawn();
+        }
     }
-}
\ No newline at end of file
+}
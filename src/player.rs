@@ -1,11 +1,13 @@
 pub mod component;
 pub mod resource;
 pub mod order;
+pub mod command;
+pub mod recruit;
 mod selection;
 
 use bevy::prelude::*;
 
-use crate::{pawn::{prelude::*, self}, tileset, world};
+use crate::{pawn::{prelude::*, self}, tileset, world::{self, generation::start::StartArea}, util::prelude::*};
 
 pub struct PlayerPlugin;
 
@@ -14,70 +16,56 @@ impl Plugin for PlayerPlugin {
         app.add_plugin(selection::SelectionPlugin)
             .add_plugin(order::OrderPlugin)
             .add_plugin(resource::ResourcePlugin)
+            .add_plugin(command::CommandPlugin)
+            .add_plugin(recruit::RecruitPlugin)
             .add_startup_system_to_stage(StartupStage::PostStartup, spawn_test_pawns);
     }
 }
 
-// NOTE: Spawn player pawns for testing purposes.
+// NOTE: Spawn player pawns for testing purposes, on the free
+//       tiles of the starting cave closest to its center.
 fn spawn_test_pawns(
     mut commands: Commands,
     mut world: ResMut<world::World>,
+    start: Res<StartArea>,
     t: Res<tileset::Tileset>,
+    registry: Res<ArchetypeRegistry>,
+    mut rng: ResMut<GameRng>,
 ) {
-    pawn::core::spawn_default_pawn_with_alignment(
-        &mut commands,
-        &mut world,
-        &t,
-        pawn::core::DEFAULT_PAWN_GLYPH,
-        (50, 35),
-        Alignment::Player,
-    );
+    let pawns = [
+        ("dwarf", Alignment::Player),
+        ("runecaster", Alignment::Player),
+        ("bat", Alignment::Neutral),
+        ("goblin", Alignment::Enemy),
+        ("worker", Alignment::Player),
+        ("worker", Alignment::Player),
+        ("worker", Alignment::Player),
+    ];
 
-    pawn::core::spawn_default_pawn_with_alignment(
-        &mut commands,
-        &mut world,
-        &t,
-        pawn::core::DEFAULT_PAWN_GLYPH,
-        (50, 39),
-        Alignment::Player,
-    );
+    let mut tiles = start.tiles.iter()
+        .copied()
+        .filter(|pos| world.get_tile(*pos).passage.is_none())
+        .collect::<Vec<_>>()
+        .into_iter();
 
-    pawn::core::spawn_default_pawn_with_alignment(
-        &mut commands,
-        &mut world,
-        &t,
-        pawn::core::DEFAULT_PAWN_GLYPH,
-        (48, 37),
-        Alignment::Neutral,
-    );
+    for (name, alignment) in pawns {
+        let position = match tiles.find(|pos| world.get_entity(*pos).is_none()) {
+            Some(p) => p,
+            None => {
+                error!("Starting cave doesn't have enough free tiles for the pawn `{}`.", name);
+                panic!();
+            }
+        };
 
-    pawn::core::spawn_default_pawn_with_alignment(
-        &mut commands,
-        &mut world,
-        &t,
-        pawn::core::DEFAULT_PAWN_GLYPH,
-        (52, 37),
-        Alignment::Enemy,
-    );
-
-    pawn::worker::spawn_worker_pawn(
-        &mut commands, 
-        &mut world, 
-        &t, 
-        (46, 37),
-    );
-
-    pawn::worker::spawn_worker_pawn(
-        &mut commands, 
-        &mut world, 
-        &t, 
-        (45, 35),
-    );
-
-    pawn::worker::spawn_worker_pawn(
-        &mut commands, 
-        &mut world, 
-        &t, 
-        (44, 37),
-    );
+        pawn::archetype::spawn_pawn_from_archetype(
+            &mut commands,
+            &mut world,
+            &t,
+            &registry,
+            name,
+            position,
+            alignment,
+            &mut rng,
+        );
+    }
 }
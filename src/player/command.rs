@@ -0,0 +1,470 @@
+use std::{collections::VecDeque, env, fs, path::PathBuf};
+use bevy::{prelude::*, asset::FileAssetIo};
+use serde::{Serialize, Deserialize};
+use log::{info, error};
+
+use crate::{
+    pawn::prelude::*,
+    world::{room::prelude::*, structure::Structure},
+    util::prelude::*,
+    turn_system,
+};
+
+pub mod prelude {
+    pub use super::{
+        PlayerCommand,
+        PlayerCommandEvent,
+        CommandEvent,
+    };
+}
+
+pub struct CommandPlugin;
+
+impl Plugin for CommandPlugin {
+    fn build(&self, app: &mut App) {
+        // NOTE: A replay overrides the world seed, this plugin must
+        //       be added after `GameRng` is inserted for the first time.
+        let replay = match read_replay_argument() {
+            Some(log) => {
+                app.insert_resource(GameRng::new(log.seed));
+
+                Replay {
+                    commands: VecDeque::from(log.commands),
+                }
+            },
+            None => Replay::default(),
+        };
+
+        app.add_event::<PlayerCommandEvent>()
+            .add_event::<CommandEvent>()
+            .insert_resource(replay)
+            .add_startup_system_to_stage(StartupStage::PreStartup, setup_command_log)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                dispatch_commands.after(turn_system::turn_system)
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, time_command.after(dispatch_commands))
+            .add_system_to_stage(CoreStage::PreUpdate, issue_time_commands)
+            .add_system_to_stage(CoreStage::PostUpdate, save_command_log);
+    }
+}
+
+// NOTE: Label of the systems that apply the commands, systems that
+//       change the tasks of the pawns on their own run after these.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApplyCommands;
+
+// NOTE: Command line argument that is used to replay a saved command log.
+const REPLAY_ARGUMENT: &str = "--replay";
+
+// NOTE: Path to the folder which saved command logs are written
+//       to, relative to the base path of the game.
+const REPLAY_FOLDER: &str = "replays";
+
+// NOTE: Every command the player can give, all player input that changes
+//       the game goes through these, so that it can be recorded and replayed.
+//       - Select -> Selects the pawns in the area on the level, in world coordinates.
+//       - Move   -> Moves the selected pawns, or attacks a hostile pawn on the target.
+//       - Cast   -> Selected pawns cast the spell at the target.
+//       - Mine   -> Marks or unmarks the tiles in the area to be mined.
+//       - Room   -> Designates or removes a room in the area.
+//       - Build  -> Orders or cancels structures on the tiles in the area.
+//       - Lock   -> Locks or unlocks the door on the target.
+//       - Recruit -> Recruits a pawn of the archetype at a portal room.
+//       - Tame   -> Tames the neutral pawn on the target.
+//       - Rally  -> Places the rally point on the target, or removes it.
+//       - Pause  -> Pauses or resumes the game.
+//       - Step   -> Passes a single turn while the game is paused.
+//       - Speed  -> Changes the speed of the game.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PlayerCommand {
+    Select { position: (f32, f32), size: (f32, f32), #[serde(default)] level: usize },
+    Move { target: Position },
+    Cast { spell: Spell, target: Position },
+    Mine { position: Position, size: Position, remove: bool },
+    Room { kind: RoomKind, position: Position, size: Position, remove: bool },
+    Build { #[serde(default)] structure: Structure, position: Position, size: Position, remove: bool },
+    Lock { target: Position },
+    Recruit { archetype: String },
+    Tame { target: Position },
+    Rally { target: Position },
+    Pause(bool),
+    Step,
+    Speed(turn_system::GameSpeed),
+}
+
+impl PlayerCommand {
+    // NOTE: Time controls only change how the game runs, not the world,
+    //       so they don't wait for a turn to pass before they're applied.
+    pub fn is_time_control(&self) -> bool {
+        return matches!(self, PlayerCommand::Pause(_) | PlayerCommand::Step | PlayerCommand::Speed(_));
+    }
+}
+
+// NOTE: Event that is sent when the player issues a command from the input,
+//       only `dispatch_commands` should read this event.
+pub struct PlayerCommandEvent(pub PlayerCommand);
+
+// NOTE: Event that is sent when a command is applied, with the first
+//       turn it affects. Every command handler reads this event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandEvent {
+    pub turn: u64,
+    pub command: PlayerCommand,
+}
+
+// NOTE: Resource that records every applied command, and the seed of the game.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct CommandLog {
+    pub seed: u64,
+    pub commands: Vec<CommandEvent>,
+}
+
+// NOTE: Resource that holds the commands of a replay which are not applied
+//       yet, player input is ignored until every command is applied.
+#[derive(Resource, Debug, Default)]
+pub struct Replay {
+    pub commands: VecDeque<CommandEvent>,
+}
+
+impl Replay {
+    pub fn active(&self) -> bool {
+        return !self.commands.is_empty();
+    }
+
+    // NOTE: Removes and returns the commands which affect the given turn,
+    //       or an earlier one. World commands are only due on the frame the
+    //       turn passes, so that they're applied before the turn is processed.
+    //       Time controls are due on any frame, otherwise a paused replay
+    //       would never reach the frame that resumes it.
+    pub fn take_due(&mut self, turn: u64, over: bool) -> Vec<CommandEvent> {
+        let mut due = vec![];
+        let mut rest = VecDeque::new();
+
+        for c in self.commands.drain(..) {
+            if c.turn <= turn && (over || c.command.is_time_control()) {
+                due.push(c);
+            } else {
+                rest.push_back(c);
+            }
+        }
+
+        self.commands = rest;
+
+        return due;
+    }
+}
+
+// NOTE: Returns the first turn a command which is applied on this frame
+//       affects. Pawns act on the frame their turn passes, after the
+//       commands are applied. On any other frame they already acted
+//       this turn, so the command only affects the next one. Time
+//       controls affect the current turn, since the turn may not pass
+//       again until they're applied.
+pub fn command_turn(command: &PlayerCommand, turn: u64, over: bool) -> u64 {
+    if over || command.is_time_control() {
+        return turn;
+    }
+
+    return turn + 1;
+}
+
+// NOTE: Reads the command log given with `--replay <path>`, if there is any.
+fn read_replay_argument() -> Option<CommandLog> {
+    let mut args = env::args().skip_while(|a| a != REPLAY_ARGUMENT).skip(1);
+
+    let path = args.next()?;
+
+    let source = match fs::read_to_string(&path) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to read the replay file `{}`: {}.", path, e);
+            panic!();
+        }
+    };
+
+    let log: CommandLog = match ron::from_str(&source) {
+        Ok(l) => l,
+        Err(e) => {
+            error!("Failed to parse the replay file `{}`: {}.", path, e);
+            panic!();
+        }
+    };
+
+    info!("Replaying {} commands from `{}`.", log.commands.len(), path);
+
+    return Some(log);
+}
+
+// NOTE: Creates the `CommandLog` resource with the seed of the game.
+fn setup_command_log(mut commands: Commands, rng: Res<GameRng>) {
+    commands.insert_resource(CommandLog {
+        seed: rng.seed(),
+        commands: vec![],
+    });
+}
+
+// NOTE: Stamps the issued commands with the first turn they affect, records
+//       and applies them. While a replay is active, the player's commands
+//       are ignored and the replayed commands are applied on the frame their
+//       turn passes, before the turn is processed like they were originally.
+//       Replayed time controls are applied as soon as their turn is reached.
+pub fn dispatch_commands(
+    mut replay: ResMut<Replay>,
+    mut log: ResMut<CommandLog>,
+    mut event_reader: EventReader<PlayerCommandEvent>,
+    mut event_writer: EventWriter<CommandEvent>,
+    mut turn_reader: EventReader<turn_system::TurnOverEvent>,
+    game_time: Res<turn_system::GameTime>,
+) {
+    let mut over = false;
+    for _ in turn_reader.iter() {
+        over = true;
+    }
+
+    let turn = game_time.turn;
+
+    if replay.active() {
+        if event_reader.iter().count() != 0 {
+            info!("Ignored player command, a replay is active.");
+        }
+
+        for e in replay.take_due(turn, over) {
+            log.commands.push(e.clone());
+            event_writer.send(e);
+        }
+
+        return;
+    }
+
+    for e in event_reader.iter() {
+        let e = CommandEvent {
+            turn: command_turn(&e.0, turn, over),
+            command: e.0.clone(),
+        };
+
+        log.commands.push(e.clone());
+        event_writer.send(e);
+    }
+}
+
+// NOTE: Time controls, space toggles the pause, N steps a single
+//       turn while paused, and minus and equals change the speed.
+fn issue_time_commands(
+    mut event_writer: EventWriter<PlayerCommandEvent>,
+    game_state: Res<turn_system::GameState>,
+    keys: Res<Input<KeyCode>>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        event_writer.send(PlayerCommandEvent(PlayerCommand::Pause(!game_state.paused())));
+    }
+
+    if keys.just_pressed(KeyCode::N) {
+        event_writer.send(PlayerCommandEvent(PlayerCommand::Step));
+    }
+
+    if keys.just_pressed(KeyCode::Equals) {
+        event_writer.send(PlayerCommandEvent(PlayerCommand::Speed(game_state.speed.shift(true))));
+    }
+
+    if keys.just_pressed(KeyCode::Minus) {
+        event_writer.send(PlayerCommandEvent(PlayerCommand::Speed(game_state.speed.shift(false))));
+    }
+}
+
+// NOTE: Pauses, resumes or steps the game, and changes its speed.
+fn time_command(
+    mut game_state: ResMut<turn_system::GameState>,
+    mut event_reader: EventReader<CommandEvent>,
+) {
+    for e in event_reader.iter() {
+        match e.command {
+            PlayerCommand::Pause(paused) => {
+                game_state.state = if paused {
+                    turn_system::GameStateID::Pause
+                } else {
+                    turn_system::GameStateID::Active
+                };
+            },
+            PlayerCommand::Step => {
+                if game_state.paused() {
+                    game_state.step = true;
+                }
+            },
+            PlayerCommand::Speed(speed) => game_state.set_speed(speed),
+            _ => {},
+        }
+    }
+}
+
+// NOTE: Saves the command log into the replay folder with the F5 key.
+fn save_command_log(
+    log: Res<CommandLog>,
+    game_time: Res<turn_system::GameTime>,
+    keys: Res<Input<KeyCode>>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    let folder = FileAssetIo::get_base_path().join(REPLAY_FOLDER);
+
+    if let Err(e) = fs::create_dir_all(&folder) {
+        error!("Failed to create the replay folder `{}`: {}.", folder.display(), e);
+        return;
+    }
+
+    let path: PathBuf = folder.join(format!("replay_{}_{}.ron", log.seed, game_time.turn));
+
+    let source = match ron::ser::to_string_pretty(&*log, ron::ser::PrettyConfig::default()) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("Failed to serialize the command log: {}.", e);
+            return;
+        }
+    };
+
+    match fs::write(&path, source) {
+        Ok(_) => info!("Saved {} commands to `{}`.", log.commands.len(), path.display()),
+        Err(e) => error!("Failed to write the replay file `{}`: {}.", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // NOTE: Frames of a game, with the turn after `turn_system` and whether it passed.
+    const FRAMES: [(u64, bool); 8] = [
+        (0, false),
+        (1, true),
+        (1, false),
+        (1, false),
+        (2, true),
+        (3, true),
+        (3, false),
+        (4, true),
+    ];
+
+    #[test]
+    fn replay_round_trip() {
+        let mut log = vec![];
+        let mut applied = vec![];
+
+        // NOTE: A command is issued on every frame of the live game.
+        for (frame, (turn, over)) in FRAMES.iter().enumerate() {
+            let command = PlayerCommand::Move { target: Position::new(frame as i32, 0, 0) };
+
+            log.push(CommandEvent {
+                turn: command_turn(&command, *turn, *over),
+                command,
+            });
+
+            applied.push(if *over { *turn } else { *turn + 1 });
+        }
+
+        let mut replay = Replay { commands: VecDeque::from(log) };
+        let mut replayed = vec![];
+
+        for (turn, over) in FRAMES {
+            for e in replay.take_due(turn, over) {
+                replayed.push(if over { turn } else { turn + 1 });
+
+                assert_eq!(e.turn, turn);
+            }
+        }
+
+        assert!(!replay.active());
+        assert_eq!(applied, replayed);
+    }
+
+    // NOTE: Runs frames of a game which passes a turn every other frame
+    //       unless it's paused, and returns the commands that were applied
+    //       with the first turn they affect. Commands are either issued
+    //       by the player on a frame, or taken from the replay.
+    fn run_frames(
+        frames: usize,
+        issued: &[(usize, PlayerCommand)],
+        mut replay: Option<&mut Replay>,
+    ) -> Vec<CommandEvent> {
+        let mut turn = 0;
+        let mut paused = false;
+        let mut step = false;
+        let mut applied = vec![];
+
+        for frame in 0..frames {
+            let over = if paused { step } else { frame % 2 == 1 };
+            step = false;
+
+            if over {
+                turn += 1;
+            }
+
+            let commands = match replay.as_mut() {
+                Some(r) => r.take_due(turn, over),
+                None => issued
+                    .iter()
+                    .filter(|(f, _)| *f == frame)
+                    .map(|(_, c)| CommandEvent { turn: command_turn(c, turn, over), command: c.clone() })
+                    .collect(),
+            };
+
+            for e in commands {
+                match e.command {
+                    PlayerCommand::Pause(p) => paused = p,
+                    PlayerCommand::Step => step = paused,
+                    _ => {}
+                }
+
+                applied.push(e);
+            }
+        }
+
+        return applied;
+    }
+
+    #[test]
+    fn replay_paused_round_trip() {
+        let target = |x| PlayerCommand::Move { target: Position::new(x, 0, 0) };
+
+        // NOTE: The game is paused for a while, a command is issued and a
+        //       turn is stepped while paused, then the game is resumed.
+        let issued = vec![
+            (2, target(0)),
+            (3, PlayerCommand::Pause(true)),
+            (6, target(1)),
+            (8, PlayerCommand::Step),
+            (12, target(2)),
+            (15, PlayerCommand::Pause(false)),
+            (18, target(3)),
+        ];
+
+        let live = run_frames(24, &issued, None);
+        assert_eq!(live.len(), issued.len());
+
+        let mut replay = Replay { commands: VecDeque::from(live.clone()) };
+        let replayed = run_frames(24, &[], Some(&mut replay));
+
+        assert!(!replay.active());
+
+        let world = |events: &[CommandEvent]| {
+            return events
+                .iter()
+                .filter(|e| !e.command.is_time_control())
+                .map(|e| (e.turn, format!("{:?}", e.command)))
+                .collect::<Vec<_>>();
+        };
+
+        assert_eq!(world(&live), world(&replayed));
+    }
+
+    #[test]
+    fn command_turn_after_turn_over() {
+        let command = PlayerCommand::Lock { target: Position::new(0, 0, 0) };
+
+        assert_eq!(command_turn(&command, 5, true), 5);
+        assert_eq!(command_turn(&command, 5, false), 6);
+        assert_eq!(command_turn(&PlayerCommand::Pause(true), 5, false), 5);
+        assert_eq!(command_turn(&PlayerCommand::Step, 5, true), 5);
+    }
+}
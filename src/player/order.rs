@@ -3,9 +3,10 @@ use bevy::prelude::*;
 use log::info;
 
 use crate::{
-    pawn::{prelude::*, core, worker}, 
-    util::cursor, world,
-    player::selection::prelude::*, globals, tileset,
+    pawn::{prelude::*, core, worker, combat}, 
+    util::cursor, world::{self, room::prelude::*, level::prelude::*, tile::prelude::*, structure::{Structure, TrapKind}},
+    player::{selection::prelude::*, command::{prelude::*, dispatch_commands, ApplyCommands}, resource::PlayerResources},
+    globals, tileset,
 };
 
 pub struct OrderPlugin;
@@ -13,10 +14,20 @@ pub struct OrderPlugin;
 #[allow(unused_variables)]
 impl Plugin for OrderPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_to_stage(CoreStage::PreUpdate, select_pawns)
-            .add_system_to_stage(CoreStage::PreUpdate, move_order)
-            .add_system_to_stage(CoreStage::PreUpdate, mine_order)
-            .add_system_to_stage(CoreStage::PreUpdate, prepare_selection);
+        app.add_system_to_stage(CoreStage::PreUpdate, select_pawns.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, move_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, mine_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, room_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, build_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, cast_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, lock_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, rally_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, issue_selection_commands)
+            .add_system_to_stage(CoreStage::PreUpdate, issue_order_commands)
+            .add_system_to_stage(CoreStage::PreUpdate, issue_cursor_commands)
+            .add_system_to_stage(CoreStage::PreUpdate, prepare_selection)
+            .add_system_to_stage(CoreStage::PreUpdate, prepare_spell_targeting)
+            .init_resource::<SpellTargeting>();
     }
 }
 
@@ -36,6 +47,51 @@ const MINE_ORDER_SELECTION_COLORS: [Color; 2] = [
     Color::rgba(1.0, 0.1, 0.1, 0.05),
 ];
 
+// NOTE: Tag that is used to detect room indicators.
+#[derive(Component)]
+pub struct RoomIndicator;
+
+// NOTE: Colors for the selection that will be used for room order.
+const ROOM_ORDER_SELECTION_COLORS: [Color; 2] = [
+    Color::rgba(0.1, 1.0, 1.0, 0.05),
+    Color::rgba(1.0, 0.1, 0.1, 0.05),
+];
+
+// NOTE: Tag that is used to detect build order indicators,
+//       holds the structure that is ordered on the tile.
+#[derive(Component)]
+pub struct BuildOrderIndicator(pub Structure);
+
+// NOTE: Color of the build order indicator entities.
+const BUILD_ORDER_INDICATOR_COLOR: Color = Color::rgba(0.6, 0.6, 0.6, 0.4);
+
+// NOTE: Colors for the selection that will be used for build order.
+const BUILD_ORDER_SELECTION_COLORS: [Color; 2] = [
+    Color::rgba(0.6, 0.6, 0.6, 0.05),
+    Color::rgba(1.0, 0.1, 0.1, 0.05),
+];
+
+// NOTE: Tag that is used to detect the rally point indicator.
+#[derive(Component)]
+pub struct RallyPointIndicator;
+
+// NOTE: Color of the rally point indicator entity.
+const RALLY_POINT_INDICATOR_COLOR: Color = Color::rgba(1.0, 0.3, 0.3, 0.8);
+
+// NOTE: Glyph that will be used for the rally point indicator.
+const RALLY_POINT_INDICATOR_GLYPH: usize = 24;
+
+// NOTE: Resource that holds the spell that will be cast
+//       with the next right click, if there is any.
+#[derive(Resource, Default)]
+pub struct SpellTargeting(pub Option<Spell>);
+
+// NOTE: Keys that are used to prepare trap build orders, in the order of `TrapKind::ALL`.
+const TRAP_KEYS: [KeyCode; 4] = [KeyCode::T, KeyCode::Y, KeyCode::U, KeyCode::I];
+
+// NOTE: Keys that are used to prepare spells, in the order of `Spell::ALL`.
+const SPELL_KEYS: [KeyCode; 3] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+
 // NOTE: Depending on the player's input prepares the selection id, 
 //       and other properties of selection.
 fn prepare_selection(
@@ -49,39 +105,188 @@ fn prepare_selection(
             snap: true,
         })
     }
+
+    if keys.just_released(KeyCode::F) {
+        event_writer.send(SelectionPrepareEvent {
+            selection_id: SelectionID::Room(RoomKind::Stockpile),
+            colors: ROOM_ORDER_SELECTION_COLORS,
+            snap: true,
+        })
+    }
+
+    if keys.just_released(KeyCode::B) {
+        event_writer.send(SelectionPrepareEvent {
+            selection_id: SelectionID::Room(RoomKind::Dormitory),
+            colors: ROOM_ORDER_SELECTION_COLORS,
+            snap: true,
+        })
+    }
+
+    if keys.just_released(KeyCode::G) {
+        event_writer.send(SelectionPrepareEvent {
+            selection_id: SelectionID::Room(RoomKind::Portal),
+            colors: ROOM_ORDER_SELECTION_COLORS,
+            snap: true,
+        })
+    }
+
+    if keys.just_released(KeyCode::P) {
+        event_writer.send(SelectionPrepareEvent {
+            selection_id: SelectionID::Build(Structure::Pillar),
+            colors: BUILD_ORDER_SELECTION_COLORS,
+            snap: true,
+        })
+    }
+
+    if keys.just_released(KeyCode::O) {
+        event_writer.send(SelectionPrepareEvent {
+            selection_id: SelectionID::Build(Structure::Door),
+            colors: BUILD_ORDER_SELECTION_COLORS,
+            snap: true,
+        })
+    }
+
+    for (key, kind) in TRAP_KEYS.iter().zip(TrapKind::ALL) {
+        if keys.just_released(*key) {
+            event_writer.send(SelectionPrepareEvent {
+                selection_id: SelectionID::Build(Structure::Trap(kind)),
+                colors: BUILD_ORDER_SELECTION_COLORS,
+                snap: true,
+            })
+        }
+    }
     
     if keys.just_pressed(KeyCode::Escape) {
         event_writer.send(SelectionPrepareEvent::default());
     }
 }
 
-// NOTE: Selects pawns under cursor or in the selection area.
-fn select_pawns(
-    mut query: Query<(&Transform, &mut Selectable), With<Pawn>>,
+// NOTE: Prepares the spell that will be cast with the next right click.
+fn prepare_spell_targeting(
+    mut targeting: ResMut<SpellTargeting>,
+    keys: Res<Input<KeyCode>>,
+) {
+    for (key, spell) in SPELL_KEYS.iter().zip(Spell::ALL) {
+        if keys.just_released(*key) {
+            targeting.0 = Some(spell);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        targeting.0 = None;
+    }
+}
+
+// NOTE: Turns the finished selections into player commands.
+//       Every selection targets the viewed level.
+fn issue_selection_commands(
     mut event_reader: EventReader<SelectionEvent>,
+    mut event_writer: EventWriter<PlayerCommandEvent>,
+    viewed: Res<ViewedLevel>,
 ) {
     for e in event_reader.iter() {
-        // NOTE: Check if the event is sent to this function.
-        let valid = match e.selection_id {
-            SelectionID::Entity => true,
-            _ => false,
+        let remove = matches!(e.selection_type, SelectionType::Negative);
+
+        let command = match (e.selection_id, &e.result) {
+            (SelectionID::Entity, SelectionResult::Default(position, size)) => {
+                PlayerCommand::Select {
+                    position: (position.x, position.y),
+                    size: (size.x, size.y),
+                    level: viewed.level,
+                }
+            },
+            (SelectionID::Mine, SelectionResult::Snap(position, size)) => {
+                let position = Position { z: viewed.level as i32, ..*position };
+
+                PlayerCommand::Mine { position, size: *size, remove }
+            },
+            (SelectionID::Room(kind), SelectionResult::Snap(position, size)) => {
+                let position = Position { z: viewed.level as i32, ..*position };
+
+                PlayerCommand::Room { kind, position, size: *size, remove }
+            },
+            (SelectionID::Build(structure), SelectionResult::Snap(position, size)) => {
+                let position = Position { z: viewed.level as i32, ..*position };
+
+                PlayerCommand::Build { structure, position, size: *size, remove }
+            },
+            _ => continue,
         };
 
-        if !valid {
-            continue;
-        }
+        event_writer.send(PlayerCommandEvent(command));
+    }
+}
+
+// NOTE: Turns right clicks into player commands, casts the prepared
+//       spell at the tile under cursor if there is any, and orders the
+//       selected pawns to move to the tile otherwise.
+fn issue_order_commands(
+    mut event_writer: EventWriter<PlayerCommandEvent>,
+    mut targeting: ResMut<SpellTargeting>,
+    cursor_pos: Res<cursor::CursorPos>,
+    buttons: Res<Input<MouseButton>>,
+    viewed: Res<ViewedLevel>,
+) {
+    if !buttons.just_released(MouseButton::Right) {
+        return;
+    }
+
+    let (x, y) = world::normalize_to_world_coordinates(cursor_pos.world);
+    let target = Position::from((x, y, viewed.level));
 
-        // NOTE: Check if the result is in the right format.
-        let (position, size) = match e.result {
-            SelectionResult::Default(position, size) => {
-                (position, size)
+    let command = match targeting.0.take() {
+        Some(spell) => PlayerCommand::Cast { spell, target },
+        None => PlayerCommand::Move { target },
+    };
+
+    event_writer.send(PlayerCommandEvent(command));
+}
+
+// NOTE: Turns the keys that act on the tile under cursor into player
+//       commands, the lock key locks or unlocks the door and the tame
+//       key tames the neutral pawn on the tile. Targets the viewed level.
+fn issue_cursor_commands(
+    mut event_writer: EventWriter<PlayerCommandEvent>,
+    cursor_pos: Res<cursor::CursorPos>,
+    keys: Res<Input<KeyCode>>,
+    viewed: Res<ViewedLevel>,
+) {
+    let (x, y) = world::normalize_to_world_coordinates(cursor_pos.world);
+    let target = Position::from((x, y, viewed.level));
+
+    if keys.just_released(KeyCode::L) {
+        event_writer.send(PlayerCommandEvent(PlayerCommand::Lock { target }));
+    }
+
+    if keys.just_released(KeyCode::K) {
+        event_writer.send(PlayerCommandEvent(PlayerCommand::Tame { target }));
+    }
+
+    if keys.just_released(KeyCode::R) {
+        event_writer.send(PlayerCommandEvent(PlayerCommand::Rally { target }));
+    }
+}
+
+// NOTE: Selects pawns under cursor or in the selection area.
+fn select_pawns(
+    mut query: Query<(&Transform, &Position, &mut Selectable), With<Pawn>>,
+    mut event_reader: EventReader<CommandEvent>,
+) {
+    for e in event_reader.iter() {
+        // NOTE: Check if the command is sent to this function.
+        let (position, size, level) = match e.command {
+            PlayerCommand::Select { position, size, level } => {
+                (Vec2::new(position.0, position.1), Vec2::new(size.0, size.1), level as i32)
             },
-            _ => {
-                continue;
-            }
+            _ => continue,
         };
 
-        for (transform, mut selectable) in &mut query {
+        for (transform, tile, mut selectable) in &mut query {
+            // NOTE: Pawns on other levels can't be selected.
+            if tile.z != level {
+                selectable.selected = false;
+                continue;
+            }
 
             // NOTE: Check if entity is in the square in both x and y coordinates.
             let x = (transform.translation.x + globals::SPRITE_SIZE) > position.x
@@ -101,18 +306,41 @@ fn select_pawns(
 }
 
 // NOTE: Sets active task of the selected entities (with `Player` tag)
-//       to move action. This function clears the task queue.
+//       to move action, or to an attack if a hostile pawn is under
+//       the cursor. This function clears the task queue. Pawns that
+//       are moved guard the target afterwards.
 fn move_order(
-    mut query: Query<(&Selectable, &Position, &Alignment, &mut TaskQueue), With<Pawn>>,
-    cursor_pos: Res<cursor::CursorPos>,
-    buttons: Res<Input<MouseButton>>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &Selectable, &Position, &Alignment, &mut TaskQueue, Option<&Abilities>), With<Pawn>>,
+    alignments: Query<&Alignment, With<Pawn>>,
+    mut event_reader: EventReader<CommandEvent>,
+    factions: Res<Factions>,
     world: Res<world::World>,
 ) {
-    if !buttons.just_released(MouseButton::Right) {
-        return;
+    for e in event_reader.iter() {
+        if let PlayerCommand::Move { target } = e.command {
+            move_selected_pawns(&mut commands, &mut query, &alignments, &factions, target, &world);
+        }
     }
+}
+
+// NOTE: Helper function for `move_order`, orders the selected pawns to the target.
+fn move_selected_pawns(
+    commands: &mut Commands,
+    query: &mut Query<(Entity, &Selectable, &Position, &Alignment, &mut TaskQueue, Option<&Abilities>), With<Pawn>>,
+    alignments: &Query<&Alignment, With<Pawn>>,
+    factions: &Factions,
+    target: Position,
+    world: &world::World,
+) {
+
+    // NOTE: Check if there is a pawn that isn't allied on the target
+    //       tile, neutral pawns are attacked only when ordered to.
+    let hostile = world.get_entity(target.into()).filter(|e| {
+        alignments.get(*e).map_or(false, |a| factions.relation(Alignment::Player, *a) != Relation::Allied)
+    });
 
-    for (selectable, position, alignment, mut task_queue) in &mut query {
+    for (entity, selectable, position, alignment, mut task_queue, abilities) in query.iter_mut() {
         let mut player_pawn = false;
         if let Alignment::Player = alignment {
             player_pawn = true;
@@ -122,9 +350,26 @@ fn move_order(
             // NOTE: Clear the active task.
             task_queue.active = Task::None;
 
-            let target: Position = world::normalize_to_world_coordinates(cursor_pos.world).into();
+            if let Some(enemy) = hostile {
+                let ranged = abilities.map_or(false, |a| a.has(Ability::Ranged));
+
+                // NOTE: Prefer ranged attacks if the target is in range,
+                //       otherwise move next to the target and attack.
+                if ranged && combat::in_attack_range(AttackKind::Ranged, position, &target, world) {
+                    task_queue.queue.push_front(Task::Shoot(enemy));
+                } else if let Some(mt) = worker::find_best_path_to_target(position, &target, world, Some(alignment), factions) {
+                    task_queue.queue.push_front(Task::Attack(enemy));
+                    task_queue.queue.push_front(Task::Move(mt));
+                } else if combat::in_attack_range(AttackKind::Melee, position, &target, world) {
+                    task_queue.queue.push_front(Task::Attack(enemy));
+                } else {
+                    info!("Ignored attack order, no possible path for given target.");
+                }
+
+                continue;
+            }
 
-            let result = core::pawn_find_path(*position, target, &world);
+            let result = core::pawn_find_path(*position, target, world, Some(alignment), factions);
 
             if let Some((mut path, _)) = result {
                 // NOTE: Remove the starting position since pawn is already on that tile.
@@ -136,6 +381,8 @@ fn move_order(
                     path: VecDeque::from(path),
                     target,
                 }));
+
+                commands.entity(entity).insert(GuardPost(target));
             } else {
                 info!("Ignored move order, no possible path for given location.");
             }
@@ -143,44 +390,58 @@ fn move_order(
     }
 }
 
+// NOTE: Orders the selected player pawns which are able to cast
+//       the spell, to cast it at the target tile.
+fn cast_order(
+    mut query: Query<(&Selectable, &Alignment, &Abilities, &mut TaskQueue), With<Pawn>>,
+    mut event_reader: EventReader<CommandEvent>,
+) {
+    for e in event_reader.iter() {
+        let (spell, target) = match e.command {
+            PlayerCommand::Cast { spell, target } => (spell, target),
+            _ => continue,
+        };
+
+        for (selectable, alignment, abilities, mut task_queue) in &mut query {
+            if !selectable.selected || *alignment != Alignment::Player {
+                continue;
+            }
+
+            if !abilities.has(Ability::Cast(spell)) {
+                continue;
+            }
+
+            task_queue.active = Task::None;
+            task_queue.queue.push_front(Task::Cast((spell, target)));
+        }
+    }
+}
+
 // NOTE: Marks the tiles under cursor or in the selection's are to be mined.
 // TODO: Refactor this mess, at some point...
 fn mine_order(
     mut commands: Commands,
     mut world: ResMut<world::World>,
-    mut event_reader: EventReader<SelectionEvent>,
+    mut event_reader: EventReader<CommandEvent>,
     mut remove_work_ew: EventWriter<worker::RemoveGlobalWorkEvent>,
     mut register_work_ew: EventWriter<worker::RegisterGlobalWorkEvent>,
     query: Query<(Entity, &Position), With<MineOrderIndicator>>,
     tileset: Res<tileset::Tileset>,
 ) {
     for e in event_reader.iter() {
-        // NOTE: Check if the event is sent to this function.
-        let valid = match e.selection_id {
-            SelectionID::Mine => true,
-            _ => false,
-        };
-
-        if valid {
-            // NOTE: Check if the result is in the right format.
-            let (position, size) = match e.result {
-                SelectionResult::Snap(position, size) => {
-                    (position, size)
-                },
-                _ => {
-                    continue;
-                }
-            };
+        // NOTE: Check if the command is sent to this function.
+        if let PlayerCommand::Mine { position, size, remove } = e.command {
 
             // NOTE: Calculate the current tile positions,
             //       and save them into a vector.
-            let mut positions: Vec<(usize, usize)> = vec![];
+            let mut positions: Vec<(usize, usize, usize)> = vec![];
 
             for y in 0..size.y {
                 for x in 0..size.x {
-                    let position: (usize, usize) = (
+                    let position: (usize, usize, usize) = (
                         (position.x + x) as usize,
                         (position.y + y) as usize,
+                        position.z as usize,
                     );
 
                     positions.push(position);
@@ -189,10 +450,17 @@ fn mine_order(
 
             // NOTE: Depending on the selection type, either
             //       delete or create new indicators.
-            match e.selection_type {
-                SelectionType::Possitive => {
+            match remove {
+                false => {
                     for position in &positions {
-                        if world.is_solid_tile(*position) && !world.get_tile(*position).marked{
+                        // NOTE: Only natural walls can be mined, doors are left alone.
+                        if !world.in_bounds(*position) {
+                            continue;
+                        }
+
+                        let tile = world.get_tile(*position);
+
+                        if tile.state == TileState::Solid && !tile.marked {
                             // NOTE: Setup the mine-task shadow entity..
                             let e = tileset::spawn_sprite_from_tileset(
                                 &mut commands,
@@ -223,7 +491,7 @@ fn mine_order(
                             register_work_ew.send(
                                 worker::RegisterGlobalWorkEvent::new(
                                     worker::GlobalWork::new(
-                                        Task::Mine((position, id.clone())),
+                                        Task::Mine(MineTask::new(position, id.clone())),
                                         id,
                                         position,
                                     ),
@@ -232,14 +500,14 @@ fn mine_order(
                         }
                     }
                 },
-                SelectionType::Negative => {
+                true => {
                     for (entity, tile) in &query {
-                        let tile: (usize, usize) = (*tile).into();
+                        let tile: (usize, usize, usize) = (*tile).into();
 
                         // NOTE: If entity's position is selected to
                         //       be removed, remove the entity.
                         for position in &positions {
-                            if tile.0 == position.0 && tile.1 == position.1 {
+                            if tile == *position {
                                 
                                 // NOTE: Despawn the entity.
                                 commands.entity(entity).despawn_recursive();
@@ -265,4 +533,267 @@ fn mine_order(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// NOTE: Designates the empty tiles in the selection's area as rooms,
+//       or removes the designation if the selection is negative.
+fn room_order(
+    mut commands: Commands,
+    mut rooms: ResMut<Rooms>,
+    mut event_reader: EventReader<CommandEvent>,
+    query: Query<(Entity, &Position), With<RoomIndicator>>,
+    world: Res<world::World>,
+    tileset: Res<tileset::Tileset>,
+) {
+    for e in event_reader.iter() {
+        // NOTE: Check if the command is sent to this function.
+        let (kind, position, size, remove) = match e.command {
+            PlayerCommand::Room { kind, position, size, remove } => (kind, position, size, remove),
+            _ => continue,
+        };
+
+        let mut positions: Vec<Position> = vec![];
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                positions.push(Position::new(position.x + x, position.y + y, position.z));
+            }
+        }
+
+        match remove {
+            false => {
+                for position in &positions {
+                    // NOTE: Only walkable tiles which aren't a room yet can be designated.
+                    if !world.is_walkable_tile((*position).into()) || rooms.get(position).is_some() {
+                        continue;
+                    }
+
+                    let e = tileset::spawn_sprite_from_tileset(
+                        &mut commands,
+                        &tileset,
+                        kind.glyph(),
+                        Vec3::new(
+                            position.x as f32 * globals::SPRITE_SIZE,
+                            position.y as f32 * globals::SPRITE_SIZE,
+                            globals::SPRITE_ORDER_WORLD + 1.0,
+                        ),
+                        Vec3::new(globals::SPRITE_SCALE, globals::SPRITE_SCALE, 1.0),
+                        kind.color(),
+                    );
+
+                    commands.entity(e)
+                        .insert(*position)
+                        .insert(RoomIndicator);
+
+                    rooms.insert(*position, kind);
+                }
+            },
+            true => {
+                for (entity, position) in &query {
+                    if positions.contains(position) {
+                        commands.entity(entity).despawn_recursive();
+
+                        rooms.remove(position);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// NOTE: Orders structures on the free walkable tiles in the selection's
+//       area, or cancels the orders if the selection is negative. Every
+//       structure is paid for when it's ordered, tiles that can't be paid
+//       for are skipped. Cancelled orders refund their own structure.
+fn build_order(
+    mut commands: Commands,
+    mut world: ResMut<world::World>,
+    mut player_resources: ResMut<PlayerResources>,
+    mut event_reader: EventReader<CommandEvent>,
+    mut remove_work_ew: EventWriter<worker::RemoveGlobalWorkEvent>,
+    mut register_work_ew: EventWriter<worker::RegisterGlobalWorkEvent>,
+    query: Query<(Entity, &Position, &BuildOrderIndicator)>,
+    tileset: Res<tileset::Tileset>,
+) {
+    for e in event_reader.iter() {
+        // NOTE: Check if the command is sent to this function.
+        let (structure, position, size, remove) = match e.command {
+            PlayerCommand::Build { structure, position, size, remove } => (structure, position, size, remove),
+            _ => continue,
+        };
+
+        let mut positions: Vec<Position> = vec![];
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                positions.push(Position::new(position.x + x, position.y + y, position.z));
+            }
+        }
+
+        match remove {
+            false => {
+                let (material, cost) = structure.cost();
+
+                for position in &positions {
+                    if !world.is_walkable_tile((*position).into()) {
+                        continue;
+                    }
+
+                    let tile = world.get_tile((*position).into());
+
+                    // NOTE: Structures can only be built on walkable tiles without other structures.
+                    if tile.marked || tile.passage.is_some() || tile.door.is_some() || tile.trap.is_some() {
+                        continue;
+                    }
+
+                    let stock = &mut player_resources.resources[material as usize].quantity;
+
+                    if *stock < cost {
+                        info!("Not enough {:?} to build a {}, build order is skipped.", material, structure.name());
+                        break;
+                    }
+
+                    *stock -= cost;
+
+                    let e = tileset::spawn_sprite_from_tileset(
+                        &mut commands,
+                        &tileset,
+                        structure.glyph(),
+                        Vec3::new(
+                            position.x as f32 * globals::SPRITE_SIZE,
+                            position.y as f32 * globals::SPRITE_SIZE,
+                            globals::SPRITE_ORDER_USER,
+                        ),
+                        Vec3::new(globals::SPRITE_SCALE, globals::SPRITE_SCALE, 1.0),
+                        BUILD_ORDER_INDICATOR_COLOR,
+                    );
+
+                    commands.entity(e)
+                        .insert(*position)
+                        .insert(BuildOrderIndicator(structure));
+
+                    world.get_tile_mut((*position).into()).marked = true;
+
+                    let id = worker::GlobalWorkID::new(worker::BUILD_WORK_IDENTIFIER, position);
+
+                    register_work_ew.send(
+                        worker::RegisterGlobalWorkEvent::new(
+                            worker::GlobalWork::new(
+                                Task::Build(BuildTask::new(*position, structure, id.clone())),
+                                id,
+                                *position,
+                            ),
+                        )
+                    );
+                }
+            },
+            true => {
+                for (entity, position, indicator) in &query {
+                    if !positions.contains(position) {
+                        continue;
+                    }
+
+                    commands.entity(entity).despawn_recursive();
+
+                    world.get_tile_mut((*position).into()).marked = false;
+
+                    let (material, cost) = indicator.0.cost();
+
+                    player_resources.resources[material as usize].quantity += cost;
+
+                    let id = worker::GlobalWorkID::new(worker::BUILD_WORK_IDENTIFIER, position);
+
+                    remove_work_ew.send(worker::RemoveGlobalWorkEvent::new(id));
+                }
+            }
+        }
+    }
+}
+
+// NOTE: Locks the door on the target tile, or unlocks it if it's
+//       already locked. Locked doors block pawns of every alignment.
+fn lock_order(
+    mut world: ResMut<world::World>,
+    mut event_reader: EventReader<CommandEvent>,
+) {
+    for e in event_reader.iter() {
+        let target: (usize, usize, usize) = match e.command {
+            PlayerCommand::Lock { target } => target.into(),
+            _ => continue,
+        };
+
+        if !world.in_bounds(target) {
+            continue;
+        }
+
+        match &mut world.get_tile_mut(target).door {
+            Some(door) => {
+                door.locked = !door.locked;
+
+                info!("The door at {:?} is {}.", target, if door.locked { "locked" } else { "unlocked" });
+            },
+            None => {
+                info!("Ignored lock order, there is no door on the target tile.");
+            }
+        }
+    }
+}
+
+// NOTE: Places the rally point on the target tile, placing it
+//       on the current rally point removes it. Pawns that retreat
+//       gather around the rally point.
+fn rally_order(
+    mut commands: Commands,
+    mut rally_point: ResMut<RallyPoint>,
+    mut event_reader: EventReader<CommandEvent>,
+    query: Query<Entity, With<RallyPointIndicator>>,
+    world: Res<world::World>,
+    tileset: Res<tileset::Tileset>,
+) {
+    for e in event_reader.iter() {
+        let target = match e.command {
+            PlayerCommand::Rally { target } => target,
+            _ => continue,
+        };
+
+        if rally_point.0 == Some(target) {
+            rally_point.0 = None;
+        } else if world.is_walkable_tile(target.into()) {
+            rally_point.0 = Some(target);
+        } else {
+            info!("Ignored rally order, the target tile isn't walkable.");
+            continue;
+        }
+
+        for entity in &query {
+            commands.entity(entity).despawn();
+        }
+
+        let target = match rally_point.0 {
+            Some(t) => t,
+            None => {
+                info!("The rally point is removed.");
+                continue;
+            }
+        };
+
+        let e = tileset::spawn_sprite_from_tileset(
+            &mut commands,
+            &tileset,
+            RALLY_POINT_INDICATOR_GLYPH,
+            Vec3::new(
+                target.x as f32 * globals::SPRITE_SIZE,
+                target.y as f32 * globals::SPRITE_SIZE,
+                globals::SPRITE_ORDER_USER,
+            ),
+            Vec3::new(globals::SPRITE_SCALE, globals::SPRITE_SCALE, 1.0),
+            RALLY_POINT_INDICATOR_COLOR,
+        );
+
+        commands.entity(e)
+            .insert(target)
+            .insert(RallyPointIndicator);
+
+        info!("The rally point is placed at {:?}.", target);
+    }
+}
@@ -0,0 +1,224 @@
+use bevy::prelude::*;
+use log::info;
+
+use crate::{
+    pawn::{prelude::*, archetype},
+    world::{self, room::prelude::*, tile::ResourceMaterial},
+    player::{command::{prelude::*, dispatch_commands, ApplyCommands}, resource::PlayerResources},
+    util::prelude::*,
+    turn_system, tileset,
+};
+
+pub struct RecruitPlugin;
+
+impl Plugin for RecruitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::PreUpdate, recruit_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::PreUpdate, tame_order.after(dispatch_commands).label(ApplyCommands))
+            .add_system_to_stage(CoreStage::Update, pay_wages);
+    }
+}
+
+// NOTE: Morale every pawn loses on a day its wage isn't paid.
+pub const UNPAID_WAGE_MORALE_PENALTY: f32 = 30.0;
+
+// NOTE: Component that holds the gold a recruited pawn is paid every day.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Wage(pub usize);
+
+// NOTE: Returns the first free walkable tile of the portal rooms.
+fn find_free_portal_tile(rooms: &Rooms, world: &world::World) -> Option<(usize, usize, usize)> {
+    return rooms.tiles.iter()
+        .filter(|(_, k)| *k == RoomKind::Portal)
+        .map(|(p, _)| (*p).into())
+        .find(|p| world.is_walkable_tile(*p) && world.get_entity(*p).is_none());
+}
+
+// NOTE: Recruits or summons a pawn of the archetype at a portal room, the
+//       cost of the archetype is paid from the player's resources. Names
+//       and stats of the recruits are rolled like every other pawn's.
+fn recruit_order(
+    mut commands: Commands,
+    mut world: ResMut<world::World>,
+    mut player_resources: ResMut<PlayerResources>,
+    mut event_reader: EventReader<CommandEvent>,
+    mut rng: ResMut<GameRng>,
+    rooms: Res<Rooms>,
+    registry: Res<ArchetypeRegistry>,
+    tileset: Res<tileset::Tileset>,
+) {
+    for e in event_reader.iter() {
+        // NOTE: Check if the command is sent to this function.
+        let name = match &e.command {
+            PlayerCommand::Recruit { archetype } => archetype.as_str(),
+            _ => continue,
+        };
+
+        let hire = match registry.get(name).and_then(|a| a.hire.as_ref()) {
+            Some(h) => h,
+            None => {
+                info!("Ignored recruit order, the archetype `{}` can't be recruited.", name);
+                continue;
+            }
+        };
+
+        let affordable = hire.cost.iter()
+            .all(|(material, cost)| player_resources.resources[*material as usize].quantity >= *cost);
+
+        if !affordable {
+            info!("Not enough resources to recruit a `{}`, recruit order is skipped.", name);
+            continue;
+        }
+
+        let position = match find_free_portal_tile(&rooms, &world) {
+            Some(p) => p,
+            None => {
+                info!("Ignored recruit order, there is no free portal tile.");
+                continue;
+            }
+        };
+
+        let entity = archetype::spawn_pawn_from_archetype(
+            &mut commands,
+            &mut world,
+            &tileset,
+            &registry,
+            name,
+            position,
+            Alignment::Player,
+            &mut rng,
+        );
+
+        let entity = match entity {
+            Some(e) => e,
+            None => continue,
+        };
+
+        for (material, cost) in &hire.cost {
+            player_resources.resources[*material as usize].quantity -= *cost;
+        }
+
+        commands.entity(entity).insert(Wage(hire.wage));
+
+        if let Some(brain) = &hire.brain {
+            commands.entity(entity).insert(Brain(brain.clone()));
+        }
+
+        info!("A `{}` arrived through the portal at {:?}.", name, position);
+    }
+}
+
+// NOTE: Tames the neutral pawn on the target tile, the pawn joins the player
+//       if its archetype can be tamed and the cost is paid. Provoked pawns
+//       have to calm down before they can be tamed. Tamed pawns switch to
+//       the brain of their tame, pawns without one only act on orders.
+fn tame_order(
+    mut commands: Commands,
+    mut query: Query<(&mut Alignment, &ArchetypeName, Option<&Provoked>), With<Pawn>>,
+    mut player_resources: ResMut<PlayerResources>,
+    mut event_reader: EventReader<CommandEvent>,
+    world: Res<world::World>,
+    factions: Res<Factions>,
+    registry: Res<ArchetypeRegistry>,
+) {
+    for e in event_reader.iter() {
+        // NOTE: Check if the command is sent to this function.
+        let target: (usize, usize, usize) = match e.command {
+            PlayerCommand::Tame { target } => target.into(),
+            _ => continue,
+        };
+
+        let entity = match world.get_entity(target) {
+            Some(e) => e,
+            None => continue,
+        };
+
+        let (mut alignment, archetype, provoked) = match query.get_mut(entity) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        if factions.relation(Alignment::Player, *alignment) != Relation::Neutral || provoked.is_some() {
+            info!("Ignored tame order, only calm neutral pawns can be tamed.");
+            continue;
+        }
+
+        let tame = match registry.get(&archetype.0).and_then(|a| a.tame.as_ref()) {
+            Some(t) => t,
+            None => {
+                info!("Ignored tame order, the archetype `{}` can't be tamed.", archetype.0);
+                continue;
+            }
+        };
+
+        let affordable = tame.cost.iter()
+            .all(|(material, cost)| player_resources.resources[*material as usize].quantity >= *cost);
+
+        if !affordable {
+            info!("Not enough resources to tame a `{}`, tame order is skipped.", archetype.0);
+            continue;
+        }
+
+        for (material, cost) in &tame.cost {
+            player_resources.resources[*material as usize].quantity -= *cost;
+        }
+
+        *alignment = Alignment::Player;
+
+        commands.entity(entity).insert(Wage(tame.wage));
+
+        match &tame.brain {
+            Some(brain) => commands.entity(entity).insert(Brain(brain.clone())),
+            None => commands.entity(entity).remove::<Brain>(),
+        };
+
+        info!("The `{}` at {:?} was tamed.", archetype.0, target);
+    }
+}
+
+// NOTE: Pays the wages of the recruited pawns from the player's gold at
+//       the start of every day, in the order of the entities. Pawns that
+//       can't be paid lose morale instead.
+fn pay_wages(
+    mut query: Query<(Entity, &Wage, Option<&mut Needs>), With<Pawn>>,
+    mut player_resources: ResMut<PlayerResources>,
+    mut event_reader: EventReader<turn_system::NewDayEvent>,
+) {
+    let mut day = None;
+    for e in event_reader.iter() {
+        day = Some(e.day);
+    }
+
+    let day = match day {
+        Some(d) => d,
+        None => return,
+    };
+
+    let mut entities: Vec<Entity> = query.iter().map(|q| q.0).collect();
+    entities.sort();
+
+    let gold = &mut player_resources.resources[ResourceMaterial::Gold as usize].quantity;
+    let (mut paid, mut unpaid) = (0, 0);
+
+    for entity in entities {
+        let (_, wage, needs) = match query.get_mut(entity) {
+            Ok(r) => r,
+            Err(_) => continue,
+        };
+
+        if *gold >= wage.0 {
+            *gold -= wage.0;
+            paid += wage.0;
+
+            continue;
+        }
+
+        unpaid += 1;
+
+        if let Some(mut needs) = needs {
+            needs.morale = (needs.morale - UNPAID_WAGE_MORALE_PENALTY).max(0.0);
+        }
+    }
+
+    info!("Paid `{}` gold in wages on day {}, `{}` pawns went unpaid.", paid, day, unpaid);
+}
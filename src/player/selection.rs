@@ -7,6 +7,7 @@ use crate::{
     world, 
     ui::inspector,
     pawn::prelude::*,
+    world::{room::prelude::*, structure::Structure},
 };
 
 pub mod prelude {
@@ -58,7 +59,8 @@ pub enum SelectionID {
     Invalid,
     Entity,
     Mine,
-    Build,
+    Build(Structure),
+    Room(RoomKind),
 }
 
 #[allow(dead_code)]
@@ -339,10 +341,15 @@ fn control_selection(
         }
 
         // NOTE: Calcualte the result depending on the snap flag.
+        // NOTE: Snapped results are on the surface, the
+        //       receiver has to move them to the right level.
         let result = if selection.snap {
+            let position = world::normalize_to_world_coordinates(position);
+            let size = world::normalize_to_world_coordinates(size.abs());
+
             SelectionResult::Snap(
-                world::normalize_to_world_coordinates(position).into(), 
-                world::normalize_to_world_coordinates(size.abs()).into()
+                Position::from((position.0, position.1, 0)), 
+                Position::from((size.0, size.1, 0))
             )
         } else {
             SelectionResult::Default(position, size.abs())
@@ -11,7 +11,7 @@ impl Plugin for TilesetPlugin {
 }
 
 // NOTE: Path to the tileset image.
-const TILESET_PATH: &str = "tileset.png";
+pub const TILESET_PATH: &str = "tileset.png";
 
 // NOTE: A resource that holds a reference to sprite atlas.
 #[derive(Resource)]
@@ -69,3 +69,27 @@ pub fn spawn_sprite_from_tileset(
         })
         .id();
 }
+
+// NOTE: Returns the texture coordinates of a glyph in the tileset,
+//       top left first and bottom right second. Coordinates are
+//       inset slightly to prevent bleeding from neighbour glyphs.
+pub fn glyph_uv(index: usize) -> (Vec2, Vec2) {
+    const INSET: f32 = 0.01;
+
+    let size = Vec2::new(
+        1.0 / globals::ATLAS_SIZE.0 as f32,
+        1.0 / globals::ATLAS_SIZE.1 as f32,
+    );
+
+    let inset = Vec2::new(
+        INSET / (globals::ATLAS_SIZE.0 * globals::SPRITE_IMAGE_SIZE.0) as f32,
+        INSET / (globals::ATLAS_SIZE.1 * globals::SPRITE_IMAGE_SIZE.1) as f32,
+    );
+
+    let min = Vec2::new(
+        (index % globals::ATLAS_SIZE.0) as f32 * size.x,
+        (index / globals::ATLAS_SIZE.0) as f32 * size.y,
+    );
+
+    return (min + inset, min + size - inset);
+}
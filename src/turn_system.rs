@@ -1,14 +1,18 @@
-use std::time::Duration;
+use std::{time::Duration, collections::BTreeMap};
 
-use bevy::prelude::*;
+use bevy::{prelude::*, ecs::event::Event};
+use serde::{Serialize, Deserialize};
+use log::info;
 
 pub struct TurnSystemPlugin;
 
 impl Plugin for TurnSystemPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<TurnOverEvent>()
+            .add_event::<NewDayEvent>()
             .add_startup_system_to_stage(StartupStage::PreStartup, setup_turn_sytem)
-            .add_system_to_stage(CoreStage::PreUpdate, turn_system);
+            .add_system_to_stage(CoreStage::PreUpdate, turn_system)
+            .add_system_to_stage(CoreStage::PreUpdate, run_scheduled_jobs.after(turn_system));
     }
 }
 
@@ -17,8 +21,163 @@ const INITIAL_GAME_STATE: GameStateID = GameStateID::Active;
 // NOTE: Time between turns in milliseconds.
 const TURN_INTERVAL: u64 = 400;
 
-// NOTE: Event that is triggered at the end of every turn.
-pub struct TurnOverEvent;
+// NOTE: Length of the in-game hours and days.
+pub const TURNS_PER_HOUR: u64 = 10;
+pub const HOURS_PER_DAY: u64 = 24;
+pub const TURNS_PER_DAY: u64 = TURNS_PER_HOUR * HOURS_PER_DAY;
+
+// NOTE: Speeds the game can run at, the turn interval is divided by the
+//       multiplier of the speed. At max speed a turn passes every frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameSpeed {
+    Normal,
+    Fast,
+    Faster,
+    Max,
+}
+
+impl GameSpeed {
+    pub const ALL: [GameSpeed; 4] = [
+        GameSpeed::Normal,
+        GameSpeed::Fast,
+        GameSpeed::Faster,
+        GameSpeed::Max,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameSpeed::Normal => "1x",
+            GameSpeed::Fast => "2x",
+            GameSpeed::Faster => "4x",
+            GameSpeed::Max => "Max",
+        }
+    }
+
+    // NOTE: Time between turns at this speed, `None` for max speed.
+    pub fn interval(&self) -> Option<Duration> {
+        match self {
+            GameSpeed::Normal => Some(Duration::from_millis(TURN_INTERVAL)),
+            GameSpeed::Fast => Some(Duration::from_millis(TURN_INTERVAL / 2)),
+            GameSpeed::Faster => Some(Duration::from_millis(TURN_INTERVAL / 4)),
+            GameSpeed::Max => None,
+        }
+    }
+
+    // NOTE: Returns the next or the previous speed, clamped to the ends.
+    pub fn shift(&self, faster: bool) -> GameSpeed {
+        let i = GameSpeed::ALL.iter().position(|s| s == self).unwrap_or(0);
+
+        let i = if faster {
+            (i + 1).min(GameSpeed::ALL.len() - 1)
+        } else {
+            i.saturating_sub(1)
+        };
+
+        return GameSpeed::ALL[i];
+    }
+}
+
+#[allow(dead_code)]
+// NOTE: Event that is triggered at the end of every turn, with the number of the turn.
+pub struct TurnOverEvent {
+    pub turn: u64,
+}
+
+#[allow(dead_code)]
+// NOTE: Event that is triggered at the start of every in-game day.
+pub struct NewDayEvent {
+    pub day: u64,
+}
+
+// NOTE: Resource that holds the number of turns passed since the
+//       start of the game, and the in-game calendar derived from it.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct GameTime {
+    pub turn: u64,
+}
+
+#[allow(dead_code)]
+impl GameTime {
+    // NOTE: Current day, starting from the first day.
+    pub fn day(&self) -> u64 {
+        return self.turn / TURNS_PER_DAY + 1;
+    }
+
+    // NOTE: Current hour of the day.
+    pub fn hour(&self) -> u64 {
+        return (self.turn / TURNS_PER_HOUR) % HOURS_PER_DAY;
+    }
+
+    // NOTE: Returns the turn that is the given number of turns later.
+    pub fn after(&self, turns: u64) -> u64 {
+        return self.turn + turns;
+    }
+}
+
+// NOTE: A callback that is run by the `Scheduler` with exclusive access to the world.
+pub type ScheduledCallback = Box<dyn FnMut(&mut World) + Send + Sync>;
+
+// NOTE: A scheduled callback, periodic jobs are rescheduled after every run.
+struct ScheduledJob {
+    callback: ScheduledCallback,
+    period: Option<u64>,
+}
+
+// NOTE: Resource that runs callbacks on a future turn, jobs are run
+//       right after the turn counter reaches their turn. Jobs that are
+//       scheduled for a past turn are run as soon as possible.
+#[derive(Resource, Default)]
+pub struct Scheduler {
+    jobs: BTreeMap<u64, Vec<ScheduledJob>>,
+}
+
+#[allow(dead_code)]
+impl Scheduler {
+    // NOTE: Runs the callback once, on the given turn.
+    pub fn schedule(&mut self, turn: u64, callback: impl FnMut(&mut World) + Send + Sync + 'static) {
+        self.push(turn, ScheduledJob {
+            callback: Box::new(callback),
+            period: None,
+        });
+    }
+
+    // NOTE: Runs the callback on the given turn, and then every `period` turns.
+    pub fn schedule_repeating(
+        &mut self,
+        turn: u64,
+        period: u64,
+        callback: impl FnMut(&mut World) + Send + Sync + 'static,
+    ) {
+        self.push(turn, ScheduledJob {
+            callback: Box::new(callback),
+            period: Some(period.max(1)),
+        });
+    }
+
+    // NOTE: Sends the event once, on the given turn.
+    pub fn schedule_event<E: Event + Clone>(&mut self, turn: u64, event: E) {
+        self.schedule(turn, move |world| world.send_event(event.clone()));
+    }
+
+    // NOTE: Returns the number of jobs waiting to be run.
+    pub fn len(&self) -> usize {
+        return self.jobs.values().map(|j| j.len()).sum();
+    }
+
+    fn push(&mut self, turn: u64, job: ScheduledJob) {
+        self.jobs.entry(turn).or_default().push(job);
+    }
+
+    // NOTE: Removes and returns every job that is due on the given turn.
+    fn take_due(&mut self, turn: u64) -> Vec<(u64, ScheduledJob)> {
+        let later = self.jobs.split_off(&(turn + 1));
+        let due = std::mem::replace(&mut self.jobs, later);
+
+        return due.into_iter()
+            .flat_map(|(t, jobs)| jobs.into_iter().map(move |j| (t, j)))
+            .collect();
+    }
+}
 
 #[allow(dead_code)]
 // NOTE: Every possible game state.
@@ -33,28 +192,143 @@ pub enum GameStateID {
 pub struct GameState {
     pub state: GameStateID,
     pub timer: Timer,
+    pub speed: GameSpeed,
+    pub step: bool,
+}
+
+impl GameState {
+    pub fn paused(&self) -> bool {
+        return matches!(self.state, GameStateID::Pause);
+    }
+
+    // NOTE: Changes the speed and the turn interval of the game.
+    pub fn set_speed(&mut self, speed: GameSpeed) {
+        self.speed = speed;
+
+        if let Some(interval) = speed.interval() {
+            self.timer.set_duration(interval);
+            self.timer.reset();
+        }
+    }
 }
 
-// NOTE: Sets up the turn system and creates the `GameState` resource.
+// NOTE: Sets up the turn system and creates the `GameState`,
+//       `GameTime` and `Scheduler` resources.
 fn setup_turn_sytem(mut commands: Commands) {
     commands.insert_resource(GameState {
         state: INITIAL_GAME_STATE,
         timer: Timer::new(Duration::from_millis(TURN_INTERVAL), TimerMode::Repeating),
+        speed: GameSpeed::Normal,
+        step: false,
     });
+
+    commands.insert_resource(GameTime::default());
+
+    // NOTE: Announce the start of every day.
+    let mut scheduler = Scheduler::default();
+
+    scheduler.schedule_repeating(TURNS_PER_DAY, TURNS_PER_DAY, |world| {
+        let day = world.resource::<GameTime>().day();
+
+        info!("Day {} has begun.", day);
+
+        world.send_event(NewDayEvent { day });
+    });
+
+    commands.insert_resource(scheduler);
 }
 
-// NOTE: Tracks the remaining time of the current turn, and
-//       sends a `TurnOverEvent` if the timer is out.
-fn turn_system(
+// NOTE: Tracks the remaining time of the current turn, and sends a
+//       `TurnOverEvent` if the timer is out. While paused, a single
+//       turn is passed if a step is requested.
+pub fn turn_system(
     mut game_state: ResMut<GameState>,
+    mut game_time: ResMut<GameTime>,
     mut event_writer: EventWriter<TurnOverEvent>,
     time: Res<Time>,
 ) {
-    if let GameStateID::Active = game_state.state {
-        game_state.timer.tick(time.delta());
+    let over = match game_state.state {
+        GameStateID::Active => {
+            game_state.timer.tick(time.delta());
+
+            game_state.speed == GameSpeed::Max || game_state.timer.just_finished()
+        },
+        GameStateID::Pause => game_state.step,
+        GameStateID::None => false,
+    };
+
+    game_state.step = false;
 
-        if game_state.timer.just_finished() {
-            event_writer.send(TurnOverEvent);
+    if over {
+        game_time.turn += 1;
+        event_writer.send(TurnOverEvent { turn: game_time.turn });
+    }
+}
+
+// NOTE: Runs the scheduled jobs which are due, and
+//       reschedules the periodic ones for their next turn.
+fn run_scheduled_jobs(world: &mut World) {
+    let turn = world.resource::<GameTime>().turn;
+    let due = world.resource_mut::<Scheduler>().take_due(turn);
+
+    for (scheduled, mut job) in due {
+        (job.callback)(world);
+
+        if let Some(period) = job.period {
+            // NOTE: Skip the runs that are missed, if the job was late.
+            let missed = (turn - scheduled) / period;
+            let next = scheduled + (missed + 1) * period;
+
+            world.resource_mut::<Scheduler>().push(next, job);
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // NOTE: Records the jobs that are run, with the turn they are run on.
+    #[derive(Resource, Default)]
+    struct Fired(Vec<(&'static str, u64)>);
+
+    #[derive(Clone)]
+    struct Ping;
+
+    fn record(name: &'static str) -> impl FnMut(&mut World) + Send + Sync + 'static {
+        return move |world: &mut World| {
+            let turn = world.resource::<GameTime>().turn;
+            world.resource_mut::<Fired>().0.push((name, turn));
+        };
+    }
+
+    #[test]
+    fn scheduled_jobs() {
+        let mut world = World::new();
+        world.insert_resource(GameTime::default());
+        world.insert_resource(Fired::default());
+        world.init_resource::<Events<Ping>>();
+
+        let mut scheduler = Scheduler::default();
+        scheduler.schedule(3, record("once"));
+        scheduler.schedule_repeating(2, 3, record("repeat"));
+        scheduler.schedule_event(4, Ping);
+        world.insert_resource(scheduler);
+
+        let mut reader = world.resource::<Events<Ping>>().get_reader();
+
+        for turn in 1..=9 {
+            world.resource_mut::<GameTime>().turn = turn;
+
+            // NOTE: Jobs are run once, even if the system runs again on the same turn.
+            run_scheduled_jobs(&mut world);
+            run_scheduled_jobs(&mut world);
+
+            let pinged = reader.iter(world.resource::<Events<Ping>>()).count();
+            assert_eq!(pinged, if turn == 4 { 1 } else { 0 });
+        }
+
+        assert_eq!(world.resource::<Fired>().0, vec![("repeat", 2), ("once", 3), ("repeat", 5), ("repeat", 8)]);
+        assert_eq!(world.resource::<Scheduler>().len(), 1);
+    }
+}
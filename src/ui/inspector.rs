@@ -2,8 +2,11 @@ use bevy::prelude::*;
 use bevy_egui::{egui::{self, RichText, Color32}, EguiContext};
 
 use crate::{
-    pawn::{prelude::*, worker},
-    player::resource::prelude::*,
+    pawn::{prelude::*, worker, needs},
+    player::{resource::prelude::*, order, command::prelude::*},
+    turn_system::{GameState, GameSpeed, GameTime},
+    world::level::prelude::*,
+    globals,
 };
 
 pub struct InspectorPlugin;
@@ -20,10 +23,17 @@ impl Plugin for InspectorPlugin {
 fn inspector(
     mut egui_context: ResMut<EguiContext>,
     player_resources: Res<PlayerResources>,
-    query: Query<(&Selectable, &Name, &Health, &Alignment, Option<&worker::Worker>), With<Pawn>>
+    targeting: Res<order::SpellTargeting>,
+    game_state: Res<GameState>,
+    game_time: Res<GameTime>,
+    turn_order: Res<TurnOrder>,
+    registry: Res<ArchetypeRegistry>,
+    mut viewed: ResMut<ViewedLevel>,
+    mut command_ew: EventWriter<PlayerCommandEvent>,
+    query: Query<(&Selectable, &Name, &Health, &Alignment, Option<&worker::Worker>, Option<&Needs>, Option<&Skills>, Option<&ArchetypeName>, Option<&StatusEffects>, Entity), With<Pawn>>
 ) {
     // NOTE: Prepare a list to sort by status of the `Player` component.
-    let mut sorted: Vec<(&Selectable, &Name, &Health, &Alignment, Option<&worker::Worker>)> = vec![];
+    let mut sorted: Vec<(&Selectable, &Name, &Health, &Alignment, Option<&worker::Worker>, Option<&Needs>, Option<&Skills>, Option<&ArchetypeName>, Option<&StatusEffects>, Entity)> = vec![];
 
     // NOTE: Display all the selected pawns.
     for tuple in &query {
@@ -41,6 +51,9 @@ fn inspector(
         .exact_width(INSPECTOR_PANEL_SIZE)
         .resizable(false)
         .show(egui_context.ctx_mut(), |ui| {
+            time_controls(ui, &game_state, &game_time, &mut command_ew);
+            level_controls(ui, &mut viewed);
+
             ui.heading("Resources");
             ui.separator();
 
@@ -70,10 +83,16 @@ fn inspector(
                 }
             });
 
+            if let Some(spell) = targeting.0 {
+                ui.label(RichText::new(format!("Casting: {}", spell.name())).color(Color32::LIGHT_BLUE));
+            }
+
+            recruit_controls(ui, &registry, &mut command_ew);
+
             ui.heading("Inspector");
             ui.separator();
 
-            for (selectable, name, health, alignment, worker) in &sorted {
+            for (selectable, name, health, alignment, worker, needs, skills, archetype, effects, entity) in &sorted {
                 if selectable.selected {
                     ui.horizontal(|ui| {
                         let color = alignment.color32();
@@ -86,6 +105,10 @@ fn inspector(
                         ui.label(RichText::new(name.as_str()).strong());
                     });
 
+                    if let Some(a) = archetype {
+                        ui.label(RichText::new(a.0.as_str()).italics());
+                    }
+
                     ui.horizontal(|ui| {
                         ui.label("Health:");
                         ui.label(RichText::new(
@@ -93,6 +116,43 @@ fn inspector(
                         )).color(Color32::GREEN));
                     });
 
+                    if let Some(place) = turn_order.place(*entity) {
+                        ui.label(format!("Initiative : {} (#{})", turn_order.order[place - 1].1, place));
+                    }
+
+                    if let Some(n) = needs {
+                        for (label, value) in [
+                            ("Hunger", n.hunger),
+                            ("Rest", n.rest),
+                            ("Morale", n.morale),
+                        ] {
+                            ui.add(
+                                egui::ProgressBar::new(value / needs::NEED_MAXIMUM)
+                                    .text(format!("{} : {:.0}", label, value))
+                            );
+                        }
+                    }
+
+                    if let Some(s) = skills {
+                        ui.horizontal_wrapped(|ui| {
+                            for kind in Skills::ALL {
+                                ui.label(format!("{} : {}", kind.name(), s.level(kind)));
+                            }
+                        });
+                    }
+
+                    if let Some(e) = effects {
+                        if !e.0.is_empty() {
+                            ui.horizontal_wrapped(|ui| {
+                                for effect in &e.0 {
+                                    ui.label(RichText::new(
+                                        format!("{} ({})", effect.kind.name(), effect.turns)
+                                    ).color(Color32::YELLOW));
+                                }
+                            });
+                        }
+                    }
+
                     if let Some(w) = worker {
                         ui.label(format!("Work Pool | A : {}, I : {}", w.accessible.len(), w.inaccessible.len()));
                     }
@@ -100,3 +160,104 @@ fn inspector(
             }
         });
 }
+
+// NOTE: Shows the calendar, turn counter and the speed of the game, with
+//       buttons to pause, resume, step a single turn and change the speed.
+fn time_controls(
+    ui: &mut egui::Ui,
+    game_state: &GameState,
+    game_time: &GameTime,
+    command_ew: &mut EventWriter<PlayerCommandEvent>,
+) {
+    let paused = game_state.paused();
+
+    ui.label(RichText::new(
+        format!("Day {}, {:02}:00", game_time.day(), game_time.hour())
+    ).strong());
+
+    ui.horizontal(|ui| {
+        ui.label(format!("Turn : {}", game_time.turn));
+        ui.separator();
+
+        if paused {
+            ui.label(RichText::new("Paused").color(Color32::YELLOW));
+        } else {
+            ui.label(format!("Speed : {}", game_state.speed.name()));
+        }
+    });
+
+    ui.horizontal(|ui| {
+        let label = if paused { "Resume" } else { "Pause" };
+
+        if ui.button(label).clicked() {
+            command_ew.send(PlayerCommandEvent(PlayerCommand::Pause(!paused)));
+        }
+
+        if ui.add_enabled(paused, egui::Button::new("Step")).clicked() {
+            command_ew.send(PlayerCommandEvent(PlayerCommand::Step));
+        }
+    });
+
+    ui.horizontal(|ui| {
+        for speed in GameSpeed::ALL {
+            if ui.selectable_label(game_state.speed == speed, speed.name()).clicked() {
+                command_ew.send(PlayerCommandEvent(PlayerCommand::Speed(speed)));
+            }
+        }
+    });
+
+    ui.separator();
+}
+
+// NOTE: Shows a button for every archetype the player can recruit,
+//       with its cost and daily wage.
+fn recruit_controls(
+    ui: &mut egui::Ui,
+    registry: &ArchetypeRegistry,
+    command_ew: &mut EventWriter<PlayerCommandEvent>,
+) {
+    ui.heading("Recruit");
+    ui.separator();
+
+    for archetype in registry.recruitable() {
+        let hire = archetype.hire.as_ref().unwrap();
+
+        let cost = hire.cost.iter()
+            .map(|(material, cost)| format!("{} {}", cost, material.identifier()))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        ui.horizontal(|ui| {
+            if ui.button(archetype.name.as_str()).clicked() {
+                command_ew.send(PlayerCommandEvent(PlayerCommand::Recruit {
+                    archetype: archetype.name.clone(),
+                }));
+            }
+
+            ui.label(format!("{} | Wage : {}", cost, hire.wage));
+        });
+    }
+
+    ui.separator();
+}
+
+// NOTE: Shows the viewed level, and lets the player switch it.
+fn level_controls(
+    ui: &mut egui::Ui,
+    viewed: &mut ViewedLevel,
+) {
+    ui.horizontal(|ui| {
+        ui.label(format!("Level : {} / {}", viewed.level + 1, globals::MAP_DEPTH));
+        ui.separator();
+
+        if ui.add_enabled(viewed.level > 0, egui::Button::new("Up")).clicked() {
+            viewed.level -= 1;
+        }
+
+        if ui.add_enabled(viewed.level + 1 < globals::MAP_DEPTH, egui::Button::new("Down")).clicked() {
+            viewed.level += 1;
+        }
+    });
+
+    ui.separator();
+}
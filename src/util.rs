@@ -1,10 +1,12 @@
 pub mod dice;
 pub mod cursor;
+pub mod random;
 
 use bevy::prelude::*;
 
 pub mod prelude {
     pub use super::cursor::CursorPos;
+    pub use super::random::prelude::*;
     pub use super::dice::{
         Die,
         Advantage,
@@ -17,6 +19,7 @@ pub struct UtilPlugin;
 
 impl Plugin for UtilPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(cursor::CursorPlugin);
+        app.add_plugin(cursor::CursorPlugin)
+            .add_plugin(random::RandomPlugin);
     }
 }
\ No newline at end of file
@@ -1,9 +1,24 @@
 #![allow(dead_code)]
 
 use core::fmt;
+use std::{collections::{BTreeMap, HashMap}, str::FromStr};
 use rand::Rng;
 use log::error;
 
+// NOTE: Maximum number of times a single exploding die can explode,
+//       this keeps both rolls and distributions finite.
+pub const EXPLOSION_LIMIT: u32 = 5;
+
+// NOTE: Maximum number of dice and sides a single term can have.
+pub const MAX_DICE_COUNT: u32 = 100;
+pub const MAX_DICE_SIDES: u32 = 1000;
+
+// NOTE: Maximum total a single term can reach, the distribution of larger
+//       terms takes too long to calculate. Keeping or dropping dice has to
+//       track how many dice are kept, so those terms are bounded lower.
+pub const MAX_TERM_TOTAL: u32 = 10_000;
+pub const MAX_KEEP_TERM_TOTAL: u32 = 1_000;
+
 // NOTE: Possible dice sides.
 #[derive(Debug, Clone, Copy)]
 pub enum Die {
@@ -80,66 +95,651 @@ pub enum Advantage {
     Advantage,
 }
 
-// NOTE: Result returned from the dice roll, contains
-//       the every dice and their total value.
-#[derive(Debug)]
-pub struct DiceRollResult(Vec<Die>, i32);
+impl Advantage {
+    // NOTE: Resolves the advantage statuses from every source of a roll,
+    //       any advantage and any dissadvantage cancel each other out
+    //       no matter how many sources grant either of them.
+    pub fn resolve(sources: impl IntoIterator<Item = Advantage>) -> Advantage {
+        let (mut advantage, mut dissadvantage) = (false, false);
+
+        for source in sources {
+            match source {
+                Advantage::Advantage => advantage = true,
+                Advantage::Dissadvantage => dissadvantage = true,
+                Advantage::Normal => {},
+            }
+        }
+
+        return match (advantage, dissadvantage) {
+            (true, false) => Advantage::Advantage,
+            (false, true) => Advantage::Dissadvantage,
+            _ => Advantage::Normal,
+        };
+    }
+}
+
+// NOTE: Which dice of a term are counted towards the total.
+//       - All            -> Every die is counted.
+//       - Highest(n)     -> Only the highest n dice are counted, `kh`.
+//       - Lowest(n)      -> Only the lowest n dice are counted, `kl`.
+//       Dropping dice (`dl`, `dh`) is parsed into keeping the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    All,
+    Highest(u32),
+    Lowest(u32),
+}
+
+// NOTE: A single group of dice in an expression, such as `4d6kh3` or `-1d4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiceTerm {
+    pub count: u32,
+    pub sides: u32,
+    pub keep: Keep,
+    pub exploding: bool,
+    pub negative: bool,
+}
+
+impl DiceTerm {
+    pub fn new(count: u32, die: Die) -> Self {
+        Self {
+            count,
+            sides: die.sides(),
+            keep: Keep::All,
+            exploding: false,
+            negative: false,
+        }
+    }
+
+    // NOTE: Number of dice that are counted towards the total.
+    pub fn kept(&self) -> u32 {
+        return match self.keep {
+            Keep::All => self.count,
+            Keep::Highest(n) | Keep::Lowest(n) => n.min(self.count),
+        };
+    }
+
+    // NOTE: Returns the highest total the term can reach.
+    fn maximum(&self) -> u32 {
+        let die = if self.exploding { self.sides * (EXPLOSION_LIMIT + 1) } else { self.sides };
+        return self.count * die;
+    }
+
+    // NOTE: Rolls every die of the term, and marks the dice that are kept.
+    fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<DieRoll> {
+        let mut dice: Vec<DieRoll> = (0..self.count).map(|_| {
+            let mut rolls = vec![rng.gen_range(1..=self.sides)];
+
+            // NOTE: Exploding dice are rolled again every time they roll their maximum.
+            while self.exploding
+                && *rolls.last().unwrap() == self.sides
+                && rolls.len() as u32 <= EXPLOSION_LIMIT {
+                rolls.push(rng.gen_range(1..=self.sides));
+            }
+
+            DieRoll {
+                sides: self.sides,
+                rolls,
+                kept: true,
+                negative: self.negative,
+            }
+        }).collect();
+
+        let mut order: Vec<usize> = (0..dice.len()).collect();
+
+        match self.keep {
+            Keep::All => return dice,
+            Keep::Highest(_) => order.sort_by_key(|i| std::cmp::Reverse(dice[*i].value())),
+            Keep::Lowest(_) => order.sort_by_key(|i| dice[*i].value()),
+        }
+
+        for i in order.into_iter().skip(self.kept() as usize) {
+            dice[i].kept = false;
+        }
+
+        return dice;
+    }
+
+    // NOTE: Returns the probability of every value a single die of the term can take.
+    fn die_distribution(&self) -> Vec<(u32, f64)> {
+        let p = 1.0 / self.sides as f64;
+
+        if !self.exploding {
+            return (1..=self.sides).map(|v| (v, p)).collect();
+        }
+
+        let mut result = vec![];
+
+        for depth in 0..=EXPLOSION_LIMIT {
+            let chance = p.powi(depth as i32 + 1);
+            let base = depth * self.sides;
+
+            for v in 1..self.sides {
+                result.push((base + v, chance));
+            }
+
+            // NOTE: The maximum is only final when the die can't explode anymore.
+            if depth == EXPLOSION_LIMIT {
+                result.push((base + self.sides, chance));
+            }
+        }
+
+        return result;
+    }
+
+    // NOTE: Returns the exact probability of every total of the term. Kept dice
+    //       are handled by distributing the dice over their possible values in
+    //       order, from the best value to the worst, and counting the first ones.
+    fn distribution(&self) -> BTreeMap<i32, f64> {
+        let sign = if self.negative { -1 } else { 1 };
+        let mut values = self.die_distribution();
+
+        // NOTE: When every die is kept the total is a plain sum of
+        //       the dice, so the dice are added one at a time.
+        if let Keep::All = self.keep {
+            let highest = values.iter().map(|(v, _)| *v as usize).max().unwrap_or(0);
+            let mut totals = vec![1.0];
+
+            for _ in 0..self.count {
+                let mut next = vec![0.0; totals.len() + highest];
+
+                for (sum, q) in totals.iter().enumerate() {
+                    for (value, p) in &values {
+                        next[sum + *value as usize] += q * p;
+                    }
+                }
+
+                totals = next;
+            }
+
+            return totals
+                .into_iter()
+                .enumerate()
+                .filter(|(_, q)| *q > 0.0)
+                .map(|(sum, q)| (sign * sum as i32, q))
+                .collect();
+        }
+
+        if let Keep::Lowest(_) = self.keep {
+            values.sort_by_key(|(v, _)| *v);
+        } else {
+            values.sort_by_key(|(v, _)| std::cmp::Reverse(*v));
+        }
+
+        let n = self.count;
+        let k = self.kept();
+
+        // NOTE: Maps the number of dice assigned so far and the sum of the kept ones.
+        let mut states: HashMap<(u32, u32), f64> = HashMap::from([((0, 0), 1.0)]);
+
+        for (value, p) in values {
+            let mut next: HashMap<(u32, u32), f64> = HashMap::new();
+
+            for ((assigned, sum), q) in states {
+                for c in 0..=(n - assigned) {
+                    let kept = (assigned + c).min(k) - assigned.min(k);
+                    let chance = q * binomial(n - assigned, c) * p.powi(c as i32);
+
+                    *next.entry((assigned + c, sum + kept * value)).or_insert(0.0) += chance;
+                }
+            }
+
+            states = next;
+        }
+
+        let mut result = BTreeMap::new();
+
+        for ((assigned, sum), q) in states {
+            if assigned == n && q > 0.0 {
+                *result.entry(sign * sum as i32).or_insert(0.0) += q;
+            }
+        }
+
+        return result;
+    }
+}
+
+impl fmt::Display for DiceTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
 
-// NOTE: Helper function that calculates the result of a single
-//       dice roll, without considering the advantage.
-fn roll_dice(count: u32, die: Die, bonus: i32) -> DiceRollResult {
-    let mut rng = rand::thread_rng();
+        if self.exploding {
+            write!(f, "!")?;
+        }
 
-    let mut rolls = vec![];
-    let mut total: i32 = 0;
+        match self.keep {
+            Keep::All => Ok(()),
+            Keep::Highest(n) => write!(f, "kh{}", n),
+            Keep::Lowest(n) => write!(f, "kl{}", n),
+        }
+    }
+}
 
-    for _ in 0..count {
-        let sides: u32 = die.sides();
-        let result = rng.gen_range(0..sides);
+// NOTE: A dice expression, which is the sum of its terms
+//       and a flat bonus, such as `2d6+1d4+3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceExpression {
+    pub terms: Vec<DiceTerm>,
+    pub bonus: i32,
+}
 
-        total += result as i32;
-        rolls.push(Die::from_sides(sides, result));
+impl DiceExpression {
+    // NOTE: Creates an expression from a single group of dice and a bonus.
+    pub fn new(count: u32, die: Die, bonus: i32) -> Self {
+        Self {
+            terms: vec![DiceTerm::new(count, die)],
+            bonus,
+        }
     }
 
-    return DiceRollResult(rolls, total + bonus);
+    // NOTE: Rolls the expression, with advantage the expression is
+    //       rolled twice and the higher total is used, and the lower
+    //       one with dissadvantage.
+    pub fn roll<R: Rng + ?Sized>(&self, advantage: Advantage, rng: &mut R) -> DiceRollResult {
+        let first = self.roll_once(rng);
+
+        let second = match advantage {
+            Advantage::Normal => return first,
+            _ => self.roll_once(rng),
+        };
+
+        let higher = first.total >= second.total;
+
+        return match (advantage, higher) {
+            (Advantage::Advantage, true) | (Advantage::Dissadvantage, false) => first,
+            _ => second,
+        };
+    }
+
+    // NOTE: Helper function that rolls the expression, without considering the advantage.
+    fn roll_once<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceRollResult {
+        let dice: Vec<DieRoll> = self.terms.iter().flat_map(|t| t.roll(rng)).collect();
+
+        let total = dice.iter()
+            .filter(|d| d.kept)
+            .map(|d| d.signed_value())
+            .sum::<i32>() + self.bonus;
+
+        return DiceRollResult {
+            dice,
+            bonus: self.bonus,
+            total,
+        };
+    }
+
+    // NOTE: Returns the exact probability of every possible total.
+    pub fn distribution(&self, advantage: Advantage) -> BTreeMap<i32, f64> {
+        let mut result = BTreeMap::from([(self.bonus, 1.0)]);
+
+        for term in &self.terms {
+            result = convolve(&result, &term.distribution());
+        }
+
+        return match advantage {
+            Advantage::Normal => result,
+            Advantage::Advantage => best_of_two(&result, true),
+            Advantage::Dissadvantage => best_of_two(&result, false),
+        };
+    }
+
+    // NOTE: Returns the expected total of the expression.
+    pub fn expected_value(&self, advantage: Advantage) -> f64 {
+        return self.distribution(advantage).iter()
+            .map(|(v, p)| *v as f64 * p)
+            .sum();
+    }
+
+    // NOTE: Returns the probability of rolling at least the given total.
+    pub fn chance_at_least(&self, target: i32, advantage: Advantage) -> f64 {
+        return self.distribution(advantage).range(target..)
+            .map(|(_, p)| p)
+            .sum();
+    }
 }
 
-// NOTE: A function that simulates dice roll.
-pub fn roll(count: u32, die: Die, bonus: i32, advantage: Advantage) -> DiceRollResult {
-    match advantage {
-        Advantage::Dissadvantage => {
-            let results = (
-                roll_dice(count, die, bonus),
-                roll_dice(count, die, bonus),
-            );
+impl fmt::Display for DiceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, term) in self.terms.iter().enumerate() {
+            if term.negative {
+                write!(f, "-")?;
+            } else if i != 0 {
+                write!(f, "+")?;
+            }
 
-            if results.0.1 < results.1.1 {
-                results.0
-            } else {
-                results.1
+            write!(f, "{}", term)?;
+        }
+
+        if self.bonus != 0 || self.terms.is_empty() {
+            if self.bonus >= 0 && !self.terms.is_empty() {
+                write!(f, "+")?;
             }
-        },
-        Advantage::Normal => {
-            return roll_dice(count, die, bonus);
-        },
-        Advantage::Advantage => {
-            let results = (
-                roll_dice(count, die, bonus),
-                roll_dice(count, die, bonus),
-            );
-
-            if results.0.1 > results.1.1 {
-                results.0
+
+            write!(f, "{}", self.bonus)?;
+        }
+
+        return Ok(());
+    }
+}
+
+// NOTE: Errors that can occur while parsing a dice expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiceParseError {
+    Empty,
+    UnexpectedCharacter(char, usize),
+    InvalidNumber(usize),
+    InvalidDiceCount(u32),
+    InvalidDiceSides(u32),
+    InvalidKeep(u32, u32),
+    TermTooLarge(u32, u32),
+}
+
+impl fmt::Display for DiceParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiceParseError::Empty => write!(f, "expression is empty"),
+            DiceParseError::UnexpectedCharacter(c, i) => write!(f, "unexpected character `{}` at {}", c, i),
+            DiceParseError::InvalidNumber(i) => write!(f, "expected a number at {}", i),
+            DiceParseError::InvalidDiceCount(c) => write!(f, "invalid dice count {}", c),
+            DiceParseError::InvalidDiceSides(s) => write!(f, "invalid dice sides {}", s),
+            DiceParseError::InvalidKeep(k, c) => write!(f, "can't keep or drop {} of {} dice", k, c),
+            DiceParseError::TermTooLarge(c, s) => write!(f, "term {}d{} can reach too high of a total", c, s),
+        }
+    }
+}
+
+impl FromStr for DiceExpression {
+    type Err = DiceParseError;
+
+    // NOTE: Parses dice notation, whitespace is ignored and letters are case insensitive.
+    //       - `NdS`    -> Rolls N dice with S sides, N defaults to one.
+    //       - `NdS!`   -> Exploding dice, maximum rolls are rolled again and added.
+    //       - `khX`    -> Keeps the highest X dice, `k` is a shorthand.
+    //       - `klX`    -> Keeps the lowest X dice.
+    //       - `dlX`    -> Drops the lowest X dice.
+    //       - `dhX`    -> Drops the highest X dice.
+    //       - `+`, `-` -> Adds or subtracts terms and flat bonuses.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<(usize, char)> = s.char_indices()
+            .filter(|(_, c)| !c.is_whitespace())
+            .map(|(i, c)| (i, c.to_ascii_lowercase()))
+            .collect();
+
+        if chars.is_empty() {
+            return Err(DiceParseError::Empty);
+        }
+
+        let mut parser = Parser { chars, cursor: 0 };
+        let mut expression = DiceExpression { terms: vec![], bonus: 0 };
+
+        let mut negative = parser.eat('-');
+        if !negative {
+            parser.eat('+');
+        }
+
+        loop {
+            let count = parser.number();
+
+            if parser.eat('d') {
+                let count = count.unwrap_or(1);
+                let sides = match parser.number() {
+                    Some(s) => s,
+                    None => return Err(DiceParseError::InvalidNumber(parser.position())),
+                };
+
+                if count == 0 || count > MAX_DICE_COUNT {
+                    return Err(DiceParseError::InvalidDiceCount(count));
+                }
+
+                if sides == 0 || sides > MAX_DICE_SIDES {
+                    return Err(DiceParseError::InvalidDiceSides(sides));
+                }
+
+                let mut term = DiceTerm {
+                    count,
+                    sides,
+                    keep: Keep::All,
+                    exploding: parser.eat('!'),
+                    negative,
+                };
+
+                term.keep = parser.keep(count)?;
+
+                let limit = match term.keep {
+                    Keep::All => MAX_TERM_TOTAL,
+                    _ => MAX_KEEP_TERM_TOTAL,
+                };
+
+                if term.maximum() > limit {
+                    return Err(DiceParseError::TermTooLarge(count, sides));
+                }
+
+                expression.terms.push(term);
             } else {
-                results.1
+                match count {
+                    Some(v) => {
+                        // NOTE: Numbers are parsed unsigned, clamp them before
+                        //       they're signed so that large ones don't wrap.
+                        let v = v.min(i32::MAX as u32) as i32;
+
+                        expression.bonus = if negative {
+                            expression.bonus.saturating_sub(v)
+                        } else {
+                            expression.bonus.saturating_add(v)
+                        };
+                    },
+                    None => return Err(DiceParseError::InvalidNumber(parser.position())),
+                }
+            }
+
+            if parser.done() {
+                break;
             }
+
+            negative = if parser.eat('+') {
+                false
+            } else if parser.eat('-') {
+                true
+            } else {
+                let (i, c) = parser.chars[parser.cursor];
+                return Err(DiceParseError::UnexpectedCharacter(c, i));
+            };
+        }
+
+        return Ok(expression);
+    }
+}
+
+// NOTE: Helper that walks over the characters of a dice expression.
+struct Parser {
+    chars: Vec<(usize, char)>,
+    cursor: usize,
+}
+
+impl Parser {
+    fn done(&self) -> bool {
+        return self.cursor >= self.chars.len();
+    }
+
+    fn position(&self) -> usize {
+        return self.chars.get(self.cursor).map_or_else(
+            || self.chars.last().map_or(0, |(i, _)| i + 1),
+            |(i, _)| *i,
+        );
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        return self.chars.get(self.cursor + offset).map(|(_, c)| *c);
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek(0) == Some(c) {
+            self.cursor += 1;
+            return true;
+        }
+
+        return false;
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let mut value: Option<u32> = None;
+
+        while let Some(d) = self.peek(0).and_then(|c| c.to_digit(10)) {
+            value = Some(value.unwrap_or(0).saturating_mul(10).saturating_add(d));
+            self.cursor += 1;
+        }
+
+        return value;
+    }
+
+    // NOTE: Parses the optional keep or drop modifier of a term.
+    fn keep(&mut self, count: u32) -> Result<Keep, DiceParseError> {
+        let (kind, drop) = match (self.peek(0), self.peek(1)) {
+            (Some('k'), Some('h')) => (Keep::Highest(0), false),
+            (Some('k'), Some('l')) => (Keep::Lowest(0), false),
+            (Some('d'), Some('l')) => (Keep::Highest(0), true),
+            (Some('d'), Some('h')) => (Keep::Lowest(0), true),
+            (Some('k'), _) => {
+                self.cursor += 1;
+                return self.keep_amount(Keep::Highest(0), false, count);
+            },
+            _ => return Ok(Keep::All),
+        };
+
+        self.cursor += 2;
+        return self.keep_amount(kind, drop, count);
+    }
+
+    fn keep_amount(&mut self, kind: Keep, drop: bool, count: u32) -> Result<Keep, DiceParseError> {
+        let amount = match self.number() {
+            Some(n) => n,
+            None => return Err(DiceParseError::InvalidNumber(self.position())),
+        };
+
+        if amount > count || (!drop && amount == 0) || (drop && amount == count) {
+            return Err(DiceParseError::InvalidKeep(amount, count));
+        }
+
+        let kept = if drop { count - amount } else { amount };
+
+        return Ok(match kind {
+            Keep::Lowest(_) => Keep::Lowest(kept),
+            _ => Keep::Highest(kept),
+        });
+    }
+}
+
+// NOTE: A single rolled die, exploding dice hold every roll they made.
+#[derive(Debug, Clone)]
+pub struct DieRoll {
+    pub sides: u32,
+    pub rolls: Vec<u32>,
+    pub kept: bool,
+    pub negative: bool,
+}
+
+impl DieRoll {
+    pub fn value(&self) -> u32 {
+        return self.rolls.iter().sum();
+    }
+
+    pub fn signed_value(&self) -> i32 {
+        let value = self.value() as i32;
+        return if self.negative { -value } else { value };
+    }
+}
+
+// NOTE: Result returned from the dice roll, contains every
+//       rolled die, the flat bonus and the total value.
+#[derive(Debug, Clone)]
+pub struct DiceRollResult {
+    pub dice: Vec<DieRoll>,
+    pub bonus: i32,
+    pub total: i32,
+}
+
+impl fmt::Display for DiceRollResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dice: Vec<String> = self.dice.iter().map(|d| {
+            let rolls: Vec<String> = d.rolls.iter().map(|r| r.to_string()).collect();
+            let sign = if d.negative { "-" } else { "" };
+            let rolls = format!("{}{}", sign, rolls.join("!"));
+
+            if d.kept { rolls } else { format!("~{}", rolls) }
+        }).collect();
+
+        write!(f, "[{}] {:+} = {}", dice.join(", "), self.bonus, self.total)
+    }
+}
+
+// NOTE: Returns the number of ways to choose k items from n.
+fn binomial(n: u32, k: u32) -> f64 {
+    let k = k.min(n - k);
+    return (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64);
+}
+
+// NOTE: Returns the distribution of the sum of two independent distributions.
+fn convolve(a: &BTreeMap<i32, f64>, b: &BTreeMap<i32, f64>) -> BTreeMap<i32, f64> {
+    let mut result = BTreeMap::new();
+
+    for (va, pa) in a {
+        for (vb, pb) in b {
+            *result.entry(va + vb).or_insert(0.0) += pa * pb;
         }
     }
+
+    return result;
+}
+
+// NOTE: Returns the distribution of the higher (or lower) of two independent rolls.
+fn best_of_two(distribution: &BTreeMap<i32, f64>, higher: bool) -> BTreeMap<i32, f64> {
+    let mut result = BTreeMap::new();
+    let mut below = 0.0;
+
+    for (v, p) in distribution {
+        let at_most = below + p;
+
+        let chance = if higher {
+            at_most * at_most - below * below
+        } else {
+            (1.0 - below) * (1.0 - below) - (1.0 - at_most) * (1.0 - at_most)
+        };
+
+        result.insert(*v, chance);
+        below = at_most;
+    }
+
+    return result;
+}
+
+// NOTE: Parses a dice expression, errors are logged and cause a panic,
+//       so this should only be used with expressions known to be valid.
+pub fn expression(s: &str) -> DiceExpression {
+    return match s.parse() {
+        Ok(e) => e,
+        Err(e) => {
+            error!("Failed to parse the dice expression `{}`: {}.", s, e);
+            panic!();
+        }
+    };
+}
+
+// NOTE: A function that simulates dice roll, with the given random number
+//       generator. Game systems should pass a stream of the `GameRng`.
+//       The bonus is signed, a negative bonus can take the total below zero.
+pub fn roll<R: Rng + ?Sized>(
+    count: u32,
+    die: Die,
+    bonus: i32,
+    advantage: Advantage,
+    rng: &mut R,
+) -> DiceRollResult {
+    return DiceExpression::new(count, die, bonus).roll(advantage, rng);
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
 
     #[test]
     #[ignore]
@@ -149,8 +749,107 @@ mod test {
         let bonus = 5;
         let advantage = Advantage::Normal;
 
-        let result = roll(count, die, bonus, advantage);
+        let result = roll(count, die, bonus, advantage, &mut rand::thread_rng());
 
-        assert!((result.1 <= count as i32 * die.sides() as i32 + 5));
+        assert!((result.total <= count as i32 * die.sides() as i32 + 5));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn roll_range() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..1000 {
+            let result = roll(1, Die::D6(0), 0, Advantage::Normal, &mut rng);
+            assert!((1..=6).contains(&result.total));
+        }
+    }
+
+    #[test]
+    fn roll_seeded() {
+        let e = expression("4d6dl1+1d8!-2");
+
+        let a = e.roll(Advantage::Advantage, &mut StdRng::seed_from_u64(42));
+        let b = e.roll(Advantage::Advantage, &mut StdRng::seed_from_u64(42));
+
+        assert_eq!(a.total, b.total);
+        assert_eq!(a.dice.iter().filter(|d| !d.kept).count(), 1);
+    }
+
+    #[test]
+    fn parse_expression() {
+        let e: DiceExpression = "2d6 + 1d4 + 3".parse().unwrap();
+
+        assert_eq!(e.terms.len(), 2);
+        assert_eq!(e.terms[1].sides, 4);
+        assert_eq!(e.bonus, 3);
+
+        assert_eq!(expression("4d6dl1").terms[0].keep, Keep::Highest(3));
+        assert_eq!(expression("2d20kl1").terms[0].keep, Keep::Lowest(1));
+        assert_eq!(expression("3D6!K2").to_string(), "3d6!kh2");
+        assert_eq!(expression("d20-1d4-1").to_string(), "1d20-1d4-1");
+    }
+
+    #[test]
+    fn negative_bonus() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            assert!((-9..=-6).contains(&roll(1, Die::D4(0), -10, Advantage::Normal, &mut rng).total));
+            assert!((-9..=-6).contains(&expression("1d4-10").roll(Advantage::Normal, &mut rng).total));
+        }
+
+        assert_eq!(expression("-3+1-2").bonus, -4);
+        assert_eq!(expression("1d4-9999999999").bonus, -i32::MAX);
+        assert_eq!(expression("1d4-10").distribution(Advantage::Normal).keys().next(), Some(&-9));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!("".parse::<DiceExpression>(), Err(DiceParseError::Empty));
+        assert_eq!("2d".parse::<DiceExpression>(), Err(DiceParseError::InvalidNumber(2)));
+        assert_eq!("0d6".parse::<DiceExpression>(), Err(DiceParseError::InvalidDiceCount(0)));
+        assert_eq!("2d6kh3".parse::<DiceExpression>(), Err(DiceParseError::InvalidKeep(3, 2)));
+        assert_eq!("2d6x".parse::<DiceExpression>(), Err(DiceParseError::UnexpectedCharacter('x', 3)));
+        assert_eq!("100d1000".parse::<DiceExpression>(), Err(DiceParseError::TermTooLarge(100, 1000)));
+        assert_eq!("20d100!".parse::<DiceExpression>(), Err(DiceParseError::TermTooLarge(20, 100)));
+        assert_eq!("20d100kh1".parse::<DiceExpression>(), Err(DiceParseError::TermTooLarge(20, 100)));
+    }
+
+    #[test]
+    fn resolve_advantage() {
+        let (adv, dis, normal) = (Advantage::Advantage, Advantage::Dissadvantage, Advantage::Normal);
+
+        assert!(matches!(Advantage::resolve([]), Advantage::Normal));
+        assert!(matches!(Advantage::resolve([adv, normal, adv]), Advantage::Advantage));
+        assert!(matches!(Advantage::resolve([dis, dis]), Advantage::Dissadvantage));
+        assert!(matches!(Advantage::resolve([adv, dis, adv]), Advantage::Normal));
+        assert!(matches!(Advantage::resolve([dis, adv, dis]), Advantage::Normal));
+    }
+
+    #[test]
+    fn distribution() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+        let d = expression("2d6").distribution(Advantage::Normal);
+        assert!(close(d[&7], 6.0 / 36.0));
+        assert!(close(expression("2d6+3").expected_value(Advantage::Normal), 10.0));
+
+        // NOTE: Known expected value of 4d6 with the lowest die dropped.
+        assert!(close(expression("4d6dl1").expected_value(Advantage::Normal), 15869.0 / 1296.0));
+
+        assert!(close(expression("1d20").chance_at_least(11, Advantage::Normal), 0.5));
+        assert!(close(expression("1d20").expected_value(Advantage::Advantage), 13.825));
+        assert!(close(expression("2d20kh1").expected_value(Advantage::Normal), 13.825));
+
+        for e in ["3d6!", "4d8kl2-1d4", "2d10!dh1+5"] {
+            let total: f64 = expression(e).distribution(Advantage::Dissadvantage).values().sum();
+            assert!(close(total, 1.0));
+        }
+
+        // NOTE: The largest terms that are allowed.
+        let d = expression("100d100").distribution(Advantage::Normal);
+        assert!(close(d.values().sum(), 1.0));
+        assert!(close(expression("100d100").expected_value(Advantage::Normal), 5050.0));
+        assert!(close(expression("10d100kh3").distribution(Advantage::Normal).values().sum(), 1.0));
+    }
+}
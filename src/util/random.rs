@@ -0,0 +1,119 @@
+use std::env;
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use log::{info, error};
+
+use crate::globals;
+
+pub mod prelude {
+    pub use super::{
+        GameRng,
+        RngStream,
+    };
+}
+
+pub struct RandomPlugin;
+
+impl Plugin for RandomPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = read_seed_argument();
+
+        info!("Using the world seed `{}`.", seed);
+
+        app.insert_resource(GameRng::new(seed));
+    }
+}
+
+// NOTE: Command line argument that overrides `WORLD_SEED`.
+const SEED_ARGUMENT: &str = "--seed";
+
+// NOTE: Independent random number streams, every subsystem draws from
+//       its own stream so that a change in one of them does not shift
+//       the random numbers of the others.
+//       - Generation -> World generation.
+//       - Names      -> Names of new pawns.
+//       - Stats      -> Stat rolls of new pawns.
+//       - Combat     -> Attack, damage and status effect rolls.
+//       - Spells     -> Spell rolls.
+//       - AI         -> Decisions and work speed of pawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RngStream {
+    Generation,
+    Names,
+    Stats,
+    Combat,
+    Spells,
+    AI,
+}
+
+impl RngStream {
+    pub const ALL: [RngStream; 6] = [
+        RngStream::Generation,
+        RngStream::Names,
+        RngStream::Stats,
+        RngStream::Combat,
+        RngStream::Spells,
+        RngStream::AI,
+    ];
+
+    fn index(&self) -> usize {
+        return *self as usize;
+    }
+}
+
+// NOTE: Resource that holds every random number stream of the game, all of
+//       them are derived from a single seed. Every source of randomness in
+//       the game must draw from this resource, so that two runs with the
+//       same seed and the same inputs are identical.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    streams: Vec<StdRng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        let streams = RngStream::ALL.iter()
+            .map(|s| StdRng::seed_from_u64(stream_seed(seed, *s)))
+            .collect();
+
+        return Self { seed, streams };
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    // NOTE: Returns the random number generator of the given stream.
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        return &mut self.streams[stream.index()];
+    }
+}
+
+// NOTE: Derives the seed of a stream from the world seed, by mixing
+//       the stream index into the seed with a 64-bit finalizer.
+fn stream_seed(seed: u64, stream: RngStream) -> u64 {
+    let mut x = seed ^ (stream.index() as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+    return x ^ (x >> 31);
+}
+
+// NOTE: Reads the seed from the command line arguments,
+//       falls back to `WORLD_SEED` if it's not given.
+fn read_seed_argument() -> u64 {
+    let mut args = env::args().skip_while(|a| a != SEED_ARGUMENT).skip(1);
+
+    return match args.next() {
+        Some(s) => match s.parse() {
+            Ok(seed) => seed,
+            Err(_) => {
+                error!("Failed to parse the seed `{}`, seed must be an unsigned integer.", s);
+                panic!();
+            }
+        },
+        None => globals::WORLD_SEED,
+    };
+}
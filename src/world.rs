@@ -1,55 +1,254 @@
 pub mod generation;
 pub mod tile;
+pub mod room;
+pub mod level;
+pub mod chunk;
+pub mod render;
+pub mod fluid;
+pub mod support;
+pub mod structure;
 
+use std::collections::HashSet;
 use bevy::prelude::*;
 
-use pathfinding::prelude::*;
-
-use crate::globals;
+use crate::{globals, pawn::prelude::{Alignment, Factions}};
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(generation::GenerationPlugin)
-            .add_plugin(tile::TilePlugin);
+            .add_plugin(room::RoomPlugin)
+            .add_plugin(level::LevelPlugin)
+            .add_plugin(chunk::ChunkPlugin)
+            .add_plugin(render::RenderPlugin)
+            .add_plugin(fluid::FluidPlugin)
+            .add_plugin(support::SupportPlugin)
+            .add_plugin(structure::StructurePlugin);
+    }
+}
+
+// NOTE: A single layer of the world, levels are stacked on top of each
+//       other and connected by passages. Deeper levels have higher indices.
+//       Tiles and entities of a level are stored in chunks.
+pub struct Level {
+    pub chunks: Vec<chunk::Chunk>,
+}
+
+impl Level {
+    // NOTE: Splits a flat tile grid of the whole level into chunks.
+    pub fn new(tiles: Vec<tile::TileData>) -> Self {
+        let (width, height) = chunk::chunk_count();
+
+        let mut chunks: Vec<chunk::Chunk> = (0..(width * height))
+            .map(|_| chunk::Chunk::default())
+            .collect();
+
+        for (i, tile) in tiles.into_iter().enumerate() {
+            let pos = (i % globals::MAP_SIZE.0, i / globals::MAP_SIZE.0);
+            let (c, t) = chunk::chunk_index(pos);
+
+            chunks[c].tiles[t] = tile;
+        }
+
+        return Self { chunks };
     }
 }
 
 // NOTE: World resource, which holds the position
-//       data of everything in the world.
+//       data of everything in the world. Tiles whose
+//       fluids may flow on the next turn are active.
+//       Positions of the built traps are kept apart,
+//       so they can be triggered without a full scan.
 #[derive(Resource)]
 pub struct World {
-    pub grid: Grid,
-    pub tiles: Vec<tile::TileData>,
-    pub entities: Vec<Option<Entity>>,
+    pub levels: Vec<Level>,
+    pub active_fluids: HashSet<(usize, usize, usize)>,
+    pub traps: HashSet<(usize, usize, usize)>,
 }
 
 impl World {
+    // NOTE: Returns whether the position is inside of the map.
+    pub fn in_bounds(&self, pos: (usize, usize, usize)) -> bool {
+        return pos.0 < globals::MAP_SIZE.0 && pos.1 < globals::MAP_SIZE.1 && pos.2 < self.levels.len();
+    }
+
     // NOTE: Return an entity from the given world coordinates,
     //       returns None if that grid doesn't contains any entity.
-    pub fn get_entity(&self, pos: (usize, usize)) -> Option<Entity> {
-        return self.entities[(pos.1 * globals::MAP_SIZE.0 as usize) + pos.0];
+    pub fn get_entity(&self, pos: (usize, usize, usize)) -> Option<Entity> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+
+        let (c, t) = chunk::chunk_index((pos.0, pos.1));
+
+        return self.levels[pos.2].chunks[c].entities[t];
     }
 
     // NOTE: Sets data of the grid at given position.
-    pub fn set_entity(&mut self, pos: (usize, usize), value: Option<Entity>) {
-        self.entities[(pos.1 * globals::MAP_SIZE.0 as usize) + pos.0] = value;
+    pub fn set_entity(&mut self, pos: (usize, usize, usize), value: Option<Entity>) {
+        let (c, t) = chunk::chunk_index((pos.0, pos.1));
+
+        self.levels[pos.2].chunks[c].entities[t] = value;
+    }
+
+    // NOTE: Returns how a pawn with the given alignment gets through the door,
+    //       doors belong to the player so it depends on the relation to them.
+    pub fn door_access(
+        &self,
+        door: &structure::Door,
+        alignment: Option<&Alignment>,
+        factions: &Factions,
+    ) -> structure::DoorAccess {
+        return door.access(alignment.map(|a| factions.relation(Alignment::Player, *a)));
     }
 
-    // NOTE: Returns the state of a tile at given position.
-    pub fn is_solid_tile(&self, pos: (usize, usize)) -> bool {
-        return self.grid.has_vertex(pos);
+    // NOTE: Returns the state of a tile at given position for a pawn with
+    //       the given alignment, positions outside of the map are always
+    //       solid. Doors are solid unless the pawn can open them, pass None
+    //       to treat every door as closed.
+    pub fn is_solid_tile(
+        &self,
+        pos: (usize, usize, usize),
+        alignment: Option<&Alignment>,
+        factions: &Factions,
+    ) -> bool {
+        if !self.in_bounds(pos) {
+            return true;
+        }
+
+        let tile = self.get_tile(pos);
+
+        if let Some(door) = tile.door {
+            return self.door_access(&door, alignment, factions) != structure::DoorAccess::Open;
+        }
+
+        return matches!(tile.state, tile::TileState::Solid);
+    }
+
+    // NOTE: Returns whether the tile blocks the sight, doors block
+    //       it whether they're open or not, like solid tiles.
+    pub fn is_opaque_tile(&self, pos: (usize, usize, usize)) -> bool {
+        if !self.in_bounds(pos) {
+            return true;
+        }
+
+        let tile = self.get_tile(pos);
+
+        return tile.door.is_some() || matches!(tile.state, tile::TileState::Solid);
+    }
+
+    // NOTE: Returns whether a pawn with the given alignment can path through
+    //       the tile, doors that have to be broken down are passable.
+    pub fn is_passable_tile(
+        &self,
+        pos: (usize, usize, usize),
+        alignment: Option<&Alignment>,
+        factions: &Factions,
+    ) -> bool {
+        if !self.is_walkable_tile(pos) {
+            return false;
+        }
+
+        return match self.get_tile(pos).door {
+            Some(door) => self.door_access(&door, alignment, factions) != structure::DoorAccess::Blocked,
+            None => true,
+        };
+    }
+
+    // NOTE: Returns whether pawns can walk on the tile at given
+    //       position, positions outside of the map aren't walkable.
+    pub fn is_walkable_tile(&self, pos: (usize, usize, usize)) -> bool {
+        if !self.in_bounds(pos) {
+            return false;
+        }
+
+        return self.get_tile(pos).state.walkable();
+    }
+
+    // NOTE: Turns a solid tile into an empty one, fluids
+    //       around the tile may flow into it on the next turn.
+    pub fn remove_solid_tile(&mut self, pos: (usize, usize, usize)) {
+        self.get_tile_mut(pos).state = tile::TileState::Empty;
+        self.wake_fluids(pos);
+    }
+
+    // NOTE: Activates the fluids of the tile and its neighbours.
+    pub fn wake_fluids(&mut self, pos: (usize, usize, usize)) {
+        self.active_fluids.insert(pos);
+        self.active_fluids.extend(fluid::neighbours(pos));
     }
 
     // NOTE: Returns the tile in the given position.
-    pub fn get_tile(&self, pos: (usize, usize)) -> tile::TileData {
-        return self.tiles[(pos.1 * globals::MAP_SIZE.0 as usize) + pos.0];
+    pub fn get_tile(&self, pos: (usize, usize, usize)) -> tile::TileData {
+        let (c, t) = chunk::chunk_index((pos.0, pos.1));
+
+        return self.levels[pos.2].chunks[c].tiles[t];
+    }
+
+    // NOTE: Sets the tile in the given position, and marks
+    //       its chunk as dirty so that its mesh is rebuilt.
+    pub fn get_tile_mut(&mut self, pos: (usize, usize, usize)) -> &mut tile::TileData {
+        let (c, t) = chunk::chunk_index((pos.0, pos.1));
+
+        let chunk = &mut self.levels[pos.2].chunks[c];
+        chunk.dirty = true;
+
+        return &mut chunk.tiles[t];
+    }
+
+    // NOTE: Returns the position a passage on the given tile leads
+    //       to, if there is any and the level it leads to exists.
+    pub fn passage_target(&self, pos: (usize, usize, usize)) -> Option<(usize, usize, usize)> {
+        let passage = self.get_tile(pos).passage?;
+
+        let level = pos.2 as i32 + passage.offset();
+
+        if level < 0 || level as usize >= self.levels.len() {
+            return None;
+        }
+
+        return Some((pos.0, pos.1, level as usize));
     }
 
-    // NOTE: Sets the tile in the given position.
-    pub fn get_tile_mut(&mut self, pos: (usize, usize)) -> &mut tile::TileData {
-        return &mut self.tiles[(pos.1 * globals::MAP_SIZE.0 as usize) + pos.0];
+    // NOTE: Checks whether there is a line of sight between two positions,
+    //       walks the line with Bresenham's algorithm and returns false if
+    //       any solid tile is in between. End points are not checked, and
+    //       there is no line of sight between different levels.
+    pub fn has_line_of_sight(&self, from: (usize, usize, usize), to: (usize, usize, usize)) -> bool {
+        if from.2 != to.2 {
+            return false;
+        }
+
+        let (mut x, mut y) = (from.0 as i32, from.1 as i32);
+        let (tx, ty) = (to.0 as i32, to.1 as i32);
+
+        let dx = (tx - x).abs();
+        let dy = -(ty - y).abs();
+        let sx = if x < tx { 1 } else { -1 };
+        let sy = if y < ty { 1 } else { -1 };
+
+        let mut err = dx + dy;
+
+        loop {
+            if x == tx && y == ty {
+                return true;
+            }
+
+            if (x, y) != (from.0 as i32, from.1 as i32) && self.is_opaque_tile((x as usize, y as usize, from.2)) {
+                return false;
+            }
+
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
     }
 }
 
@@ -60,3 +259,15 @@ pub fn normalize_to_world_coordinates(point: Vec2) -> (usize, usize) {
         (point.y / globals::SPRITE_SIZE) as usize,
     );
 }
+
+// NOTE: Creates a world of a single level filled with the given tile state, for the tests.
+#[cfg(test)]
+pub fn test_world(state: tile::TileState) -> World {
+    let tile = tile::TileData { state, ..Default::default() };
+
+    return World {
+        levels: vec![Level::new(vec![tile; globals::MAP_SIZE.0 * globals::MAP_SIZE.1])],
+        active_fluids: HashSet::new(),
+        traps: HashSet::new(),
+    };
+}
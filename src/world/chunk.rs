@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+
+use crate::{camera::MainCamera, globals};
+use super::{tile, render, level::prelude::*};
+
+pub struct ChunkPlugin;
+
+impl Plugin for ChunkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadedChunks>()
+            .add_system_to_stage(CoreStage::Update, stream_chunks);
+    }
+}
+
+// NOTE: Size of a chunk in tiles, in both axes.
+pub const CHUNK_SIZE: usize = 20;
+
+// NOTE: Number of chunks around the visible ones that are kept
+//       loaded, so that panning doesn't show unloaded chunks.
+const CHUNK_LOAD_MARGIN: i32 = 1;
+
+// NOTE: A square piece of a level, holds the tiles and the
+//       entities of the positions in it. Tiles of a chunk are
+//       stored row by row, starting from the bottom left. Dirty
+//       chunks have their meshes rebuilt if they are loaded.
+#[derive(Clone)]
+pub struct Chunk {
+    pub tiles: Vec<tile::TileData>,
+    pub entities: Vec<Option<Entity>>,
+    pub dirty: bool,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self {
+            tiles: vec![tile::TileData::default(); CHUNK_SIZE * CHUNK_SIZE],
+            entities: vec![None; CHUNK_SIZE * CHUNK_SIZE],
+            dirty: false,
+        }
+    }
+}
+
+// NOTE: Component of the entity that holds the tile mesh of a
+//       loaded chunk, chunk position is in chunks.
+#[derive(Component)]
+pub struct TileChunk {
+    pub position: (usize, usize),
+    pub level: usize,
+}
+
+// NOTE: Resource that holds the entities of the loaded
+//       chunks, keyed by their position and their level.
+#[derive(Resource, Default)]
+pub struct LoadedChunks {
+    pub chunks: HashMap<(usize, usize, usize), Entity>,
+}
+
+// NOTE: Returns the number of chunks in a level, in both axes.
+pub fn chunk_count() -> (usize, usize) {
+    return (
+        (globals::MAP_SIZE.0 + CHUNK_SIZE - 1) / CHUNK_SIZE,
+        (globals::MAP_SIZE.1 + CHUNK_SIZE - 1) / CHUNK_SIZE,
+    );
+}
+
+// NOTE: Returns the index of the chunk that holds the given
+//       position, and the index of the position in that chunk.
+pub fn chunk_index(pos: (usize, usize)) -> (usize, usize) {
+    let chunk = (pos.1 / CHUNK_SIZE) * chunk_count().0 + pos.0 / CHUNK_SIZE;
+    let tile = (pos.1 % CHUNK_SIZE) * CHUNK_SIZE + pos.0 % CHUNK_SIZE;
+
+    return (chunk, tile);
+}
+
+// NOTE: Spawns the tile meshes of the chunks near the camera on the
+//       viewed level, and despawns the ones that are no longer needed.
+fn stream_chunks(
+    mut commands: Commands,
+    mut loaded: ResMut<LoadedChunks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    camera: Query<&Transform, With<MainCamera>>,
+    world: Res<super::World>,
+    viewed: Res<ViewedLevel>,
+    material: Res<render::TileMaterial>,
+) {
+    let transform = match camera.get_single() {
+        Ok(t) => t,
+        Err(_) => return,
+    };
+
+    let chunk_size = CHUNK_SIZE as f32 * globals::SPRITE_SIZE;
+    let (width, height) = chunk_count();
+
+    // NOTE: Calculate the range of chunks that are visible, with the margin.
+    let range = |center: f32, window: usize, count: usize| -> (usize, usize) {
+        let start = ((center - window as f32 / 2.0) / chunk_size).floor() as i32 - CHUNK_LOAD_MARGIN;
+        let end = ((center + window as f32 / 2.0) / chunk_size).floor() as i32 + CHUNK_LOAD_MARGIN;
+
+        return (start.max(0) as usize, (end.max(0) as usize).min(count - 1));
+    };
+
+    let (x0, x1) = range(transform.translation.x, globals::WINDOW_SIZE.0, width);
+    let (y0, y1) = range(transform.translation.y, globals::WINDOW_SIZE.1, height);
+
+    let level = viewed.level;
+
+    // NOTE: Despawn the chunks that are out of range, or on another level.
+    loaded.chunks.retain(|(x, y, z), entity| {
+        let keep = *z == level && (x0..=x1).contains(x) && (y0..=y1).contains(y);
+
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+
+        return keep;
+    });
+
+    // NOTE: Spawn the chunks that are in range, but not loaded yet.
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            if loaded.chunks.contains_key(&(x, y, level)) {
+                continue;
+            }
+
+            let chunk = &world.levels[level].chunks[y * width + x];
+            let mesh = meshes.add(render::build_chunk_mesh(chunk, (x, y)));
+
+            let e = render::spawn_chunk_mesh(&mut commands, mesh, &material);
+
+            commands.entity(e).insert(TileChunk {
+                position: (x, y),
+                level,
+            });
+
+            loaded.chunks.insert((x, y, level), e);
+        }
+    }
+}
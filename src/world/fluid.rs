@@ -0,0 +1,253 @@
+use bevy::prelude::*;
+
+use crate::{pawn::prelude::*, util::prelude::*, globals::MAP_SIZE, turn_system};
+use super::{tile::TileState, structure};
+
+pub struct FluidPlugin;
+
+impl Plugin for FluidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(CoreStage::Update, simulate_fluids)
+            .add_system_to_stage(
+                CoreStage::Update,
+                apply_fluid_effects.after(simulate_fluids).after(structure::trigger_traps)
+            );
+    }
+}
+
+// NOTE: Maximum depth of fluid a tile can hold, water and
+//       lava tiles act as endless sources of this depth.
+pub const MAXIMUM_FLUID_DEPTH: u8 = 7;
+
+// NOTE: Minimum depth of water that slows the pawns in it.
+pub const SLOWING_WATER_DEPTH: u8 = 4;
+
+// NOTE: Number of turns the effects of a fluid last after a pawn leaves it.
+pub const FLUID_EFFECT_TURNS: u32 = 2;
+
+// NOTE: How much of the tile color is covered by the fluid at maximum depth.
+pub const FLUID_OVERLAY_OPACITY: f32 = 0.8;
+
+// NOTE: Every kind of fluid that can flow through the tunnels.
+//       - Water -> Slows the pawns when it is deep enough.
+//       - Lava  -> Burns the pawns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FluidKind {
+    Water,
+    Lava,
+}
+
+impl FluidKind {
+    #[allow(dead_code)]
+    pub fn name(&self) -> &'static str {
+        match self {
+            FluidKind::Water => "Water",
+            FluidKind::Lava => "Lava",
+        }
+    }
+
+    // NOTE: Returns the fluid a tile is an endless source of, if any.
+    pub fn source(state: TileState) -> Option<FluidKind> {
+        match state {
+            TileState::Water => Some(FluidKind::Water),
+            TileState::Lava => Some(FluidKind::Lava),
+            _ => None,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            FluidKind::Water => Color::hex("2A5DB0").unwrap(),
+            FluidKind::Lava => Color::hex("E8571B").unwrap(),
+        }
+    }
+
+    // NOTE: Extra cost of walking through the fluid with the given depth,
+    //       pawns only walk through lava if there is no other way.
+    pub fn movement_cost(&self, depth: u8) -> u32 {
+        match self {
+            FluidKind::Water => depth as u32 / 2,
+            FluidKind::Lava => 10 + depth as u32,
+        }
+    }
+
+    // NOTE: Dice and type of the damage that is dealt to the pawns in the fluid every turn.
+    pub fn damage(&self) -> Option<(u32, Die, DamageType)> {
+        match self {
+            FluidKind::Water => None,
+            FluidKind::Lava => Some((2, Die::D6(0), DamageType::Fire)),
+        }
+    }
+
+    // NOTE: Status effect that is applied to the pawns in the fluid every turn.
+    pub fn status(&self, depth: u8) -> Option<StatusKind> {
+        match self {
+            FluidKind::Water if depth >= SLOWING_WATER_DEPTH => Some(StatusKind::Slowed),
+            FluidKind::Water => None,
+            FluidKind::Lava => Some(StatusKind::Burning),
+        }
+    }
+}
+
+// NOTE: Fluid that has flowed into a walkable tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fluid {
+    pub kind: FluidKind,
+    pub depth: u8,
+}
+
+impl Fluid {
+    pub fn movement_cost(&self) -> u32 {
+        return self.kind.movement_cost(self.depth);
+    }
+
+    // NOTE: Blends the color of the fluid over the color of the tile.
+    pub fn overlay(&self, color: Color) -> Color {
+        let t = self.depth as f32 / MAXIMUM_FLUID_DEPTH as f32 * FLUID_OVERLAY_OPACITY;
+        let fluid = self.kind.color();
+
+        return Color::rgb(
+            color.r() + (fluid.r() - color.r()) * t,
+            color.g() + (fluid.g() - color.g()) * t,
+            color.b() + (fluid.b() - color.b()) * t,
+        );
+    }
+}
+
+// NOTE: Returns the orthogonal neighbours of a position that are inside of the map.
+pub fn neighbours(pos: (usize, usize, usize)) -> Vec<(usize, usize, usize)> {
+    let mut result = Vec::with_capacity(4);
+
+    for (x, y) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        let next = (pos.0 as i32 + x, pos.1 as i32 + y);
+
+        if next.0 >= 0 && next.1 >= 0 && (next.0 as usize) < MAP_SIZE.0 && (next.1 as usize) < MAP_SIZE.1 {
+            result.push((next.0 as usize, next.1 as usize, pos.2));
+        }
+    }
+
+    return result;
+}
+
+// NOTE: Flows the fluids at the end of every turn. Only the tiles that are
+//       active are updated, a tile is activated when a tile next to it is
+//       mined or when the fluid around it changes. Each tile passes a unit
+//       of its fluid to every neighbour that is at least two units lower,
+//       water and lava tiles never run out. Fluids don't mix.
+fn simulate_fluids(
+    mut world: ResMut<super::World>,
+    mut event_reader: EventReader<turn_system::TurnOverEvent>,
+) {
+    let mut over = false;
+    for _ in event_reader.iter() {
+        over = true;
+    }
+
+    if !over || world.active_fluids.is_empty() {
+        return;
+    }
+
+    // NOTE: Update the tiles in a fixed order, so the
+    //       result doesn't depend on the order of the set.
+    let mut cells: Vec<(usize, usize, usize)> = world.active_fluids.drain().collect();
+    cells.sort();
+
+    let mut changed = vec![];
+
+    for pos in cells {
+        let tile = world.get_tile(pos);
+
+        let (kind, mut depth, source) = match (FluidKind::source(tile.state), tile.fluid) {
+            (Some(kind), _) => (kind, MAXIMUM_FLUID_DEPTH, true),
+            (None, Some(f)) if tile.state.walkable() => (f.kind, f.depth, false),
+            _ => continue,
+        };
+
+        let mut flowed = false;
+
+        for next in neighbours(pos) {
+            if !world.is_walkable_tile(next) {
+                continue;
+            }
+
+            let other = match world.get_tile(next).fluid {
+                Some(f) if f.kind != kind => continue,
+                Some(f) => f.depth,
+                None => 0,
+            };
+
+            if other + 1 >= depth {
+                continue;
+            }
+
+            world.get_tile_mut(next).fluid = Some(Fluid { kind, depth: other + 1 });
+            changed.push(next);
+            flowed = true;
+
+            if !source {
+                depth -= 1;
+            }
+        }
+
+        if !flowed {
+            continue;
+        }
+
+        changed.push(pos);
+
+        if !source {
+            world.get_tile_mut(pos).fluid = Some(Fluid { kind, depth });
+        }
+    }
+
+    for pos in changed {
+        world.wake_fluids(pos);
+    }
+}
+
+// NOTE: Applies the effects of the fluids to the pawns standing in
+//       them at the end of every turn, in the order of the entities.
+pub fn apply_fluid_effects(
+    query: Query<(Entity, &Position), With<Pawn>>,
+    world: Res<super::World>,
+    mut event_reader: EventReader<turn_system::TurnOverEvent>,
+    mut damage_ew: EventWriter<DamageEvent>,
+    mut status_ew: EventWriter<ApplyStatusEvent>,
+    mut rng: ResMut<GameRng>,
+) {
+    let mut over = false;
+    for _ in event_reader.iter() {
+        over = true;
+    }
+
+    if !over {
+        return;
+    }
+
+    let mut pawns: Vec<(Entity, Position)> = query.iter().map(|(e, p)| (e, *p)).collect();
+    pawns.sort_by_key(|(e, _)| *e);
+
+    for (entity, position) in pawns {
+        let fluid = match world.get_tile(position.into()).fluid {
+            Some(f) => f,
+            None => continue,
+        };
+
+        if let Some((count, die, damage_type)) = fluid.kind.damage() {
+            damage_ew.send(DamageEvent {
+                target: entity,
+                amount: roll(count, die, 0, Advantage::Normal, rng.stream(RngStream::Combat)).total.max(1),
+                damage_type,
+                source: None,
+            });
+        }
+
+        if let Some(kind) = fluid.kind.status(fluid.depth) {
+            status_ew.send(ApplyStatusEvent {
+                target: entity,
+                kind,
+                turns: FLUID_EFFECT_TURNS,
+            });
+        }
+    }
+}
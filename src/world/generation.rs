@@ -1,341 +1,247 @@
+pub mod caves;
+pub mod passages;
+pub mod biome;
+pub mod ore;
+pub mod fluid;
+pub mod flora;
+pub mod start;
+
+use std::collections::{HashMap, HashSet};
 use bevy::prelude::*;
-use pathfinding::prelude::*;
-use log::info;
+use log::{info, error};
 use noise::{BasicMulti, NoiseFn, Perlin};
-use rand::{Rng, rngs::StdRng, SeedableRng};
+use rand::rngs::StdRng;
 
-use super::tile::prelude::*;
-use crate::{tileset, globals::MAP_SIZE, world::tile::TileData};
+use super::{tile::prelude::*, level::prelude::*};
+use crate::{globals::{MAP_SIZE, MAP_DEPTH}, world::tile::{self, TileData}, util::prelude::*};
 
 pub struct GenerationPlugin;
 
 impl Plugin for GenerationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system_to_stage(StartupStage::Startup, generate_world);
+        app.insert_resource(GenerationPipeline::default())
+            .add_startup_system_to_stage(StartupStage::Startup, generate_world);
     }
 }
 
-// NOTE: Seed that is supplied to the noise generator,
-//       remove this after map generation system is complete.
-const GENERATION_SEED: u32 = 13;
+// NOTE: Size of the square area in tiles that the generation constants are
+//       tuned for. Larger maps are generated as if they were made of many of
+//       these areas, so the size of caves and the density of resources stay
+//       the same regardless of the map size.
+pub const GENERATION_AREA_SIZE: usize = 100;
 
-// NOTE: Multiplier that affects the size of caves in generated map.
-pub const GENERATION_CAVE_SIZE: f64 = 3.50;
-// NOTE: Threshold that decides which values are considered empty.
-pub const GENERATION_CAVE_TRESHOLD: f64 = 0.12;
+// NOTE: Offset that is applied to the noise for every level,
+//       so that the features of each level are different.
+pub const LEVEL_NOISE_OFFSET: f64 = 100.0;
 
-// NOTE: Multiplier for the size of chunks that are going to turn into stone.
-pub const STONE_CHUNK_SIZE: f64 = 5.0;
+// NOTE: Returns the number of generation areas that fit in the map.
+pub fn generation_area_count() -> usize {
+    return (MAP_SIZE.0 * MAP_SIZE.1 / GENERATION_AREA_SIZE.pow(2)).max(1);
+}
 
-// NOTE: Offset that will be applied to the noise, when calculating tile hardness.
-pub const STONE_CHUNK_NOISE_OFFSET: (f64, f64) = (STONE_CHUNK_SIZE * 2.0, STONE_CHUNK_SIZE * 2.0);
+// NOTE: A single pass of the world generation, steps are run in the order
+//       of the `GenerationPipeline` and each one builds on the previous ones.
+pub trait GenerationStep: Send + Sync {
+    fn name(&self) -> &'static str;
 
-// NOTE: Required hardness treshold a tile has to
-//       be over in order to register as a stone.
-pub const MINIMUM_STONE_HARDNES: f64 = 0.12; 
+    fn run(&self, context: &mut GenerationContext);
+}
 
-// NOTE: Minimum distance that two seperate resources could be.
-pub const MINIMUM_RESOURCE_DISTANCE: f32 = 8.0;
+// NOTE: Resource that holds the steps of the world generation. New features
+//       are added as new steps, the default pipeline generates the caves,
+//       connects the levels, assigns biomes and materials, places the ores,
+//       adds the lakes, the lava rivers and the mushroom forests, and finally
+//       picks the starting cave and carves tunnels to the ores around it.
+#[derive(Resource)]
+pub struct GenerationPipeline {
+    pub steps: Vec<Box<dyn GenerationStep>>,
+}
 
-// NOTE: How much distance reduced on each maximum attempt.
-pub const RESOURCE_DISTANCE_REDUCTION: f32 = 0.5;
+impl Default for GenerationPipeline {
+    fn default() -> Self {
+        Self {
+            steps: vec![
+                Box::new(caves::CaveStep),
+                Box::new(passages::PassageStep),
+                Box::new(biome::BiomeStep),
+                Box::new(biome::WallStep),
+                Box::new(ore::OreStep),
+                Box::new(fluid::LakeStep),
+                Box::new(fluid::LavaStep),
+                Box::new(flora::MushroomStep),
+                Box::new(start::StartStep),
+                Box::new(start::TunnelStep),
+            ],
+        }
+    }
+}
 
-// NOTE: Maximum tries for a resource location
-pub const MAXIMUM_RESOURCE_ITERATION: usize = 15;
+#[allow(dead_code)]
+impl GenerationPipeline {
+    pub fn add(&mut self, step: impl GenerationStep + 'static) {
+        self.steps.push(Box::new(step));
+    }
+
+    // NOTE: Inserts the step right after the step with the given name,
+    //       or to the end of the pipeline if there is no such step.
+    pub fn insert_after(&mut self, name: &str, step: impl GenerationStep + 'static) {
+        let index = self.steps.iter()
+            .position(|s| s.name() == name)
+            .map_or(self.steps.len(), |i| i + 1);
 
-// NOTE: Decides whether or not resources can spread to empty tile.
-pub const CAN_SPREAD_TO_FREE_TILE: bool = true;
+        self.steps.insert(index, Box::new(step));
+    }
+}
+
+// NOTE: Everything the generation steps share. Tiles of every level are
+//       stored in a flat grid until the generation is over. Positions that
+//       are taken by a feature, like a passage or an ore, are reserved so
+//       that later steps don't overwrite them.
+pub struct GenerationContext<'a> {
+    pub noise: BasicMulti<Perlin>,
+    pub rng: &'a mut StdRng,
+    pub levels: Vec<Vec<TileData>>,
+    pub biomes: Vec<Vec<biome::Biome>>,
+    pub passages: HashMap<(usize, usize, usize), Passage>,
+    pub reserved: HashSet<(usize, usize, usize)>,
+    pub veins: Vec<((usize, usize, usize), ResourceMaterial)>,
+    pub caves: Vec<usize>,
+    pub start: Option<(usize, usize, usize)>,
+}
 
 #[allow(dead_code)]
-// NOTE: Calculates the score for a given tile. This will be
-//       used for future resoureces, currently unused.
-fn calculate_position_score(
-    pos: (usize, usize), 
-    grid: &Grid,
-) -> usize {
-    let mut score = 0;
-
-    // NOTE: Calculate the score in x axis.
-    for i in 0..2 {
-        let mut pos = pos;
-        let modifier: i32 = if i == 0 { -1 } else { 1 };
-
-        while grid.has_vertex(pos) {
-            pos.0 += modifier as usize;
-            score += 1;
-        }
+impl<'a> GenerationContext<'a> {
+    // NOTE: Returns the value of the noise for a tile, scaled with the
+    //       given size and offset. Every level samples a different area.
+    pub fn sample(&self, pos: (usize, usize, usize), size: f64, offset: (f64, f64)) -> f64 {
+        return self.noise.get([
+            (pos.0 as f64) / GENERATION_AREA_SIZE as f64 * size + offset.0 + LEVEL_NOISE_OFFSET * pos.2 as f64,
+            (pos.1 as f64) / GENERATION_AREA_SIZE as f64 * size + offset.1,
+        ]);
     }
 
-    // NOTE: Calculate the score in y axis.
-    for i in 0..2 {
-        let mut pos = pos;
-        let modifier: i32 = if i == 0 { -1 } else { 1 };
+    pub fn in_bounds(&self, pos: (i32, i32)) -> bool {
+        return pos.0 >= 0 && pos.1 >= 0 && (pos.0 as usize) < MAP_SIZE.0 && (pos.1 as usize) < MAP_SIZE.1;
+    }
 
-        while grid.has_vertex(pos) {
-            pos.1 += modifier as usize;
-            score += 1;
-        }
+    pub fn walkable(&self, pos: (usize, usize, usize)) -> bool {
+        return self.tile(pos).state.walkable();
     }
 
-    return score;
-}
+    pub fn tile(&self, pos: (usize, usize, usize)) -> &TileData {
+        return &self.levels[pos.2][pos.1 * MAP_SIZE.0 + pos.0];
+    }
 
-// NOTE: Check distance between two resources, and returns a score.
-fn calculate_resource_score(
-    pos: (usize, usize),
-    res: &Vec<(usize, usize)>,
-) -> f32 {
-    let mut small: f32 = f32::MAX;
-
-    for other in res {
-        let diff = (
-            (pos.0 as i32 - other.0 as i32).abs(),
-            (pos.1 as i32 - other.1 as i32).abs(),
-        );
-
-        let distance: f32 = f32::sqrt(
-            (diff.0.pow(2) + diff.1.pow(2)) as f32
-        );
-
-        if distance < small {
-            small = distance;
-        }
+    pub fn tile_mut(&mut self, pos: (usize, usize, usize)) -> &mut TileData {
+        return &mut self.levels[pos.2][pos.1 * MAP_SIZE.0 + pos.0];
     }
 
-    return small;
-}
+    pub fn state(&self, pos: (usize, usize, usize)) -> TileState {
+        return self.tile(pos).state;
+    }
 
-// NOTE: Picks a random position from the grid
-fn pick_random_tile(
-    rng: &mut StdRng,
-    res: &Vec<(usize, usize)>,
-    grid: &Grid,
-) -> (usize, usize) {
-    let mut iter = 0;
-    let mut distance = MINIMUM_RESOURCE_DISTANCE;
-
-    loop {
-        let pos = (
-            rng.gen_range(0..MAP_SIZE.0),
-            rng.gen_range(0..MAP_SIZE.0),
-        );
-    
-        let score = calculate_resource_score(pos, res);
-    
-        if grid.has_vertex(pos) && !res.contains(&pos) && score > distance {
-            return pos;
-        } else {
-            iter += 1;
-
-            if iter == MAXIMUM_RESOURCE_ITERATION {
-                info!(
-                    "Failed to find a suitable resource position
-                     during world generation with distance `{}`.", 
-                    distance
-                );
-
-                distance -= RESOURCE_DISTANCE_REDUCTION;
-                iter = 0;
-            }
-        }
+    pub fn set_state(&mut self, pos: (usize, usize, usize), state: TileState) {
+        self.tile_mut(pos).state = state;
     }
-}
 
-// NOTE: Speards resource to nearby tiles, creating more resources.
-//       Chance for a new resource to be created is reduced in every generation.
-fn spread_resource(
-    commands: &mut Commands,
-    tile_grid: &mut Vec<TileData>,
-    rng: &mut StdRng,
-    res: &mut Vec<(usize, usize)>,
-    tileset: &tileset::Tileset,
-    grid: &Grid,
-    pos: (usize, usize),
-    material: ResourceMaterial,
-    ratio: f64,
-) {
-    if ratio < 0.0 {
-        return;
+    // NOTE: Sets the material of a tile, the quantity it yields depends on the level.
+    pub fn set_material(&mut self, pos: (usize, usize, usize), material: ResourceMaterial) {
+        self.tile_mut(pos).resource = tile::Resource {
+            material,
+            quantity: tile::resource_yield(pos.2),
+        };
     }
 
-    for y in -1..=1_i32 {
-        for x in -1..=1_i32 {
-            if x.abs() == y.abs() {
-                continue;
-            }
-
-            let spread = rng.gen_bool(ratio);
-
-            let pos = (
-                if (pos.0 as i32 + x) < 0 {
-                    continue;
-                } else {
-                    (pos.0 as i32 + x) as usize
-                },
-                if (pos.1 as i32 + y) < 0 {
-                    continue;
-                } else {
-                    (pos.1 as i32 + y) as usize
-                },
-            );
-
-            let solid = grid.has_vertex(pos);
-
-            if !spread || (!CAN_SPREAD_TO_FREE_TILE && !solid) || res.contains(&pos) {
-                continue;
-            }
-
-            res.push(pos);
-
-            super::tile::spawn_tile(
-                commands, 
-                tile_grid,
-                tileset, 
-                pos, 
-                if solid { TileState::Solid } else { TileState::Empty }, 
-                material,
-            );
-
-            spread_resource(
-                commands, 
-                tile_grid,
-                rng, 
-                res, 
-                tileset, 
-                grid, 
-                pos, 
-                material,
-                ratio - material.ratio_reduction_rate(),
-            );
-        }
+    pub fn biome(&self, pos: (usize, usize, usize)) -> biome::Biome {
+        return self.biomes[pos.2][pos.1 * MAP_SIZE.0 + pos.0];
+    }
+
+    pub fn is_reserved(&self, pos: (usize, usize, usize)) -> bool {
+        return self.reserved.contains(&pos);
+    }
+
+    pub fn reserve(&mut self, pos: (usize, usize, usize)) {
+        self.reserved.insert(pos);
     }
 }
 
-// NOTE: Generates a random world at startup using a perlin noise.
-//       This function also sets up the `World` resource.
+// NOTE: Generates a random world at startup by running every step of the
+//       `GenerationPipeline`. This function also sets up the `World` resource.
 fn generate_world(
     mut commands: Commands,
-    tileset: Res<tileset::Tileset>,
+    mut game_rng: ResMut<GameRng>,
+    pipeline: Res<GenerationPipeline>,
 ) {
-    // NOTE: Setup rng.
-    let mut rng = StdRng::seed_from_u64(GENERATION_SEED as u64);
+    // NOTE: Generate the perlin noise, seeded with the world seed.
+    let noise = BasicMulti::<Perlin>::new(game_rng.seed() as u32);
+
+    let mut context = GenerationContext {
+        noise,
+        rng: game_rng.stream(RngStream::Generation),
+        levels: vec![vec![TileData::default(); MAP_SIZE.0 * MAP_SIZE.1]; MAP_DEPTH],
+        biomes: vec![vec![biome::Biome::default(); MAP_SIZE.0 * MAP_SIZE.1]; MAP_DEPTH],
+        passages: HashMap::new(),
+        reserved: HashSet::new(),
+        veins: vec![],
+        caves: vec![0; MAP_DEPTH],
+        start: None,
+    };
+
+    for step in &pipeline.steps {
+        info!("Running the world generation step `{}`.", step.name());
+
+        step.run(&mut context);
+    }
 
-    // NOTE: Generate the perlin noise.
-    let noise = BasicMulti::<Perlin>::new(GENERATION_SEED);
+    let center = match context.start {
+        Some(s) => s,
+        None => {
+            error!("World generation finished without picking a starting cave.");
+            panic!();
+        }
+    };
 
-    // NOTE: Create a vector to store all the solid tile positions.
-    let mut world: Vec<(usize, usize)> = Vec::with_capacity(
-        MAP_SIZE.0 * MAP_SIZE.1
-    );
+    // NOTE: Starting cave is measured after every step, tunnels
+    //       and later steps may have changed its shape.
+    let mut tiles = start::flood_fill(&context, center);
 
-    for y in 0..(MAP_SIZE.1) {
-        for x in 0..(MAP_SIZE.0) {
-            // NOTE: Convert tile positions to noise positions.
-            let position: [f64; 2] = [
-                (x as f64) / MAP_SIZE.0 as f64 * GENERATION_CAVE_SIZE,
-                (y as f64) / MAP_SIZE.1 as f64 * GENERATION_CAVE_SIZE,
-            ];
-
-            // NOTE: Push solid tile position to grid
-            if noise.get(position) <= GENERATION_CAVE_TRESHOLD {
-                world.push((x, y));
-            }
-        }
-    }
+    tiles.sort_by_key(|pos| pos.0.abs_diff(center.0).pow(2) + pos.1.abs_diff(center.1).pow(2));
 
-    // NOTE: Create the tile grid.
-    let mut tile_grid = vec![TileData::default(); MAP_SIZE.0 * MAP_SIZE.1];
-
-    // NOTE: Create the world grid.
-    let grid = world.into_iter().collect::<Grid>();
-
-    // NOTE: Setup resource count.
-    const RESOURCE_COUNT: usize = 4;
-
-    let resources: [(usize, ResourceMaterial); RESOURCE_COUNT] = [
-        (rng.gen_range(ResourceMaterial::Coal.range()), ResourceMaterial::Coal),
-        (rng.gen_range(ResourceMaterial::Iron.range()), ResourceMaterial::Iron),
-        (rng.gen_range(ResourceMaterial::Gold.range()), ResourceMaterial::Gold),
-        (rng.gen_range(ResourceMaterial::Crystal.range()), ResourceMaterial::Crystal),
-    ];
-
-    // NOTE: Vector to store exhausted resource positions.
-    let mut exhausted: Vec<(usize, usize)> = vec![];
-
-    // Generate resources.
-    for i in 0..RESOURCE_COUNT {
-        let res = resources[i];
-        for _ in 0..res.0 {
-            let pos = pick_random_tile(&mut rng, &exhausted, &grid);
-
-            exhausted.push(pos);
-
-            super::tile::spawn_tile(
-                &mut commands, 
-                &mut tile_grid,
-                &tileset, 
-                pos, 
-                TileState::Solid, 
-                res.1,
-            );
-
-            spread_resource(
-                &mut commands, 
-                &mut tile_grid,
-                &mut rng, 
-                &mut exhausted, 
-                &tileset, 
-                &grid, 
-                pos, 
-                res.1,
-                res.1.ratio(),
-            );
-        }
-    }
+    // NOTE: Count the ore tiles of every material for the report.
+    let mut ores = [0_usize; 6];
 
-    // NOTE: Create non-resource tiles, they are either dirt
-    //       or stone depending on the hardness value of the tile.
-    for y in 0..(MAP_SIZE.1) {
-        for x in 0..(MAP_SIZE.0) {
-            let pos = (x, y);
-
-            if exhausted.contains(&pos) {
-                continue;
-            }
-
-            let state = if grid.has_vertex(pos) {
-                TileState::Solid
-            } else {
-                TileState::Empty
-            };
-
-            let noise_pos: [f64; 2] = [
-                (x as f64) / MAP_SIZE.0 as f64 * STONE_CHUNK_SIZE + STONE_CHUNK_NOISE_OFFSET.0,
-                (y as f64) / MAP_SIZE.1 as f64 * STONE_CHUNK_SIZE + STONE_CHUNK_NOISE_OFFSET.1,
-            ];
-            let hardness: f64 = noise.get(noise_pos);
-
-            let material = if hardness > MINIMUM_STONE_HARDNES {
-                ResourceMaterial::Stone
-            } else {
-                ResourceMaterial::Dirt
-            };
-
-            super::tile::spawn_tile(
-                &mut commands, 
-                &mut tile_grid,
-                &tileset, 
-                pos, 
-                state, 
-                material,
-            );
+    for level in &context.levels {
+        for tile in level {
+            ores[tile.resource.material as usize] += 1;
         }
     }
 
+    info!(
+        "Generated `{}` levels with `{}` passages. Caves: {:?}, Coal: {}, Iron: {}, Gold: {}, Crystal: {}, Start: {:?} with `{}` tiles.",
+        context.levels.len(),
+        context.passages.len(),
+        context.caves,
+        ores[ResourceMaterial::Coal as usize],
+        ores[ResourceMaterial::Iron as usize],
+        ores[ResourceMaterial::Gold as usize],
+        ores[ResourceMaterial::Crystal as usize],
+        center,
+        tiles.len(),
+    );
+
+    commands.insert_resource(start::StartArea {
+        center,
+        tiles,
+    });
+
     // NOTE: Setup world resource.
+    let levels = context.levels.into_iter()
+        .map(super::Level::new)
+        .collect();
+
     commands.insert_resource(super::World {
-        grid,
-        tiles: tile_grid,
-        entities: vec![None; MAP_SIZE.0 * MAP_SIZE.1],
+        levels,
+        active_fluids: HashSet::new(),
+        traps: HashSet::new(),
     });
-}
\ No newline at end of file
+}
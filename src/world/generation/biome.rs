@@ -0,0 +1,169 @@
+use crate::{globals::{MAP_SIZE, MAP_DEPTH}, world::tile::prelude::*};
+use super::{GenerationStep, GenerationContext};
+
+// NOTE: Multiplier for the size of biome regions.
+pub const BIOME_SIZE: f64 = 1.5;
+
+// NOTE: Offsets that are applied to the noise, when picking the biome.
+pub const BIOME_NOISE_OFFSET: (f64, f64) = (40.0, 40.0);
+pub const FUNGAL_NOISE_OFFSET: (f64, f64) = (60.0, 20.0);
+
+// NOTE: How much the biome noise is shifted on every level, deeper
+//       levels are more likely to be gilded or crystalline.
+pub const BIOME_LEVEL_SHIFT: f64 = 0.15;
+
+// NOTE: Treshold the fungal noise has to be over for a fungal biome.
+pub const FUNGAL_TRESHOLD: f64 = 0.25;
+
+// NOTE: Multiplier for the size of chunks that are going to turn into stone.
+pub const STONE_CHUNK_SIZE: f64 = 5.0;
+
+// NOTE: Offset that will be applied to the noise, when calculating tile hardness.
+pub const STONE_CHUNK_NOISE_OFFSET: (f64, f64) = (STONE_CHUNK_SIZE * 2.0, STONE_CHUNK_SIZE * 2.0);
+
+// NOTE: Required hardness treshold a tile has to
+//       be over in order to register as a stone.
+pub const MINIMUM_STONE_HARDNES: f64 = 0.12;
+
+// NOTE: How much the stone hardness treshold is reduced on every
+//       level, deeper levels have more stone and are harder to mine.
+pub const STONE_HARDNESS_LEVEL_REDUCTION: f64 = 0.08;
+
+// NOTE: Regions of the world with their own walls and ores.
+//       - Earthen     -> Soft dirt walls, rich in coal.
+//       - Rocky       -> Stone walls, rich in iron.
+//       - Gilded      -> Rich in gold.
+//       - Crystalline -> Hard stone walls, rich in crystal.
+//       - Fungal      -> Dirt walls and mushroom forests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Biome {
+    #[default]
+    Earthen,
+    Rocky,
+    Gilded,
+    Crystalline,
+    Fungal,
+}
+
+impl Biome {
+    #[allow(dead_code)]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Biome::Earthen => "Earthen",
+            Biome::Rocky => "Rocky",
+            Biome::Gilded => "Gilded",
+            Biome::Crystalline => "Crystalline",
+            Biome::Fungal => "Fungal",
+        }
+    }
+
+    // NOTE: Ore table of the biome, the chance of an ore vein of the
+    //       material to be accepted when it's placed in the biome.
+    pub fn ore_weight(&self, material: ResourceMaterial) -> f64 {
+        match (self, material) {
+            (Biome::Earthen, ResourceMaterial::Coal) => 1.0,
+            (Biome::Earthen, ResourceMaterial::Iron) => 0.6,
+            (Biome::Earthen, ResourceMaterial::Gold) => 0.2,
+            (Biome::Earthen, ResourceMaterial::Crystal) => 0.1,
+            (Biome::Rocky, ResourceMaterial::Coal) => 0.6,
+            (Biome::Rocky, ResourceMaterial::Iron) => 1.0,
+            (Biome::Rocky, ResourceMaterial::Gold) => 0.4,
+            (Biome::Rocky, ResourceMaterial::Crystal) => 0.2,
+            (Biome::Gilded, ResourceMaterial::Coal) => 0.3,
+            (Biome::Gilded, ResourceMaterial::Iron) => 0.5,
+            (Biome::Gilded, ResourceMaterial::Gold) => 1.0,
+            (Biome::Gilded, ResourceMaterial::Crystal) => 0.4,
+            (Biome::Crystalline, ResourceMaterial::Coal) => 0.1,
+            (Biome::Crystalline, ResourceMaterial::Iron) => 0.3,
+            (Biome::Crystalline, ResourceMaterial::Gold) => 0.5,
+            (Biome::Crystalline, ResourceMaterial::Crystal) => 1.0,
+            (Biome::Fungal, ResourceMaterial::Coal) => 0.8,
+            (Biome::Fungal, ResourceMaterial::Iron) => 0.3,
+            (Biome::Fungal, ResourceMaterial::Gold) => 0.2,
+            (Biome::Fungal, ResourceMaterial::Crystal) => 0.3,
+            (_, ResourceMaterial::Dirt | ResourceMaterial::Stone) => 0.0,
+        }
+    }
+
+    // NOTE: Added to the hardness of the tiles in the biome,
+    //       positive values mean more stone and less dirt.
+    pub fn stone_bias(&self) -> f64 {
+        match self {
+            Biome::Earthen => -0.10,
+            Biome::Rocky => 0.15,
+            Biome::Gilded => 0.05,
+            Biome::Crystalline => 0.20,
+            Biome::Fungal => -0.15,
+        }
+    }
+}
+
+// NOTE: Assigns a biome to every tile, from two low frequency noises.
+pub struct BiomeStep;
+
+impl GenerationStep for BiomeStep {
+    fn name(&self) -> &'static str {
+        return "Biomes";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        for level in 0..MAP_DEPTH {
+            for y in 0..(MAP_SIZE.1) {
+                for x in 0..(MAP_SIZE.0) {
+                    let pos = (x, y, level);
+
+                    let value = context.sample(pos, BIOME_SIZE, BIOME_NOISE_OFFSET) + BIOME_LEVEL_SHIFT * level as f64;
+                    let fungal = context.sample(pos, BIOME_SIZE, FUNGAL_NOISE_OFFSET);
+
+                    let biome = if fungal > FUNGAL_TRESHOLD {
+                        Biome::Fungal
+                    } else if value < -0.15 {
+                        Biome::Earthen
+                    } else if value < 0.15 {
+                        Biome::Rocky
+                    } else if value < 0.35 {
+                        Biome::Gilded
+                    } else {
+                        Biome::Crystalline
+                    };
+
+                    context.biomes[level][y * MAP_SIZE.0 + x] = biome;
+                }
+            }
+        }
+    }
+}
+
+// NOTE: Sets the material of every tile, they are either dirt or stone
+//       depending on the hardness value of the tile and its biome.
+pub struct WallStep;
+
+impl GenerationStep for WallStep {
+    fn name(&self) -> &'static str {
+        return "Walls";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        for level in 0..MAP_DEPTH {
+            // NOTE: Deeper levels have more stone.
+            let stone_hardness = MINIMUM_STONE_HARDNES - STONE_HARDNESS_LEVEL_REDUCTION * level as f64;
+
+            for y in 0..(MAP_SIZE.1) {
+                for x in 0..(MAP_SIZE.0) {
+                    let pos = (x, y, level);
+
+                    let hardness = context.sample(pos, STONE_CHUNK_SIZE, STONE_CHUNK_NOISE_OFFSET)
+                        + context.biome(pos).stone_bias();
+
+                    let material = if hardness > stone_hardness {
+                        ResourceMaterial::Stone
+                    } else {
+                        ResourceMaterial::Dirt
+                    };
+
+                    context.set_material(pos, material);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{globals::{MAP_SIZE, MAP_DEPTH}, world::tile::prelude::*};
+use super::{GenerationStep, GenerationContext};
+
+// NOTE: Multiplier that affects the size of caves in generated map.
+pub const GENERATION_CAVE_SIZE: f64 = 3.50;
+// NOTE: Threshold that decides which values are considered empty.
+pub const GENERATION_CAVE_TRESHOLD: f64 = 0.12;
+
+// NOTE: Carves the caves of every level out of the noise, every
+//       tile is either solid or empty after this step.
+pub struct CaveStep;
+
+impl GenerationStep for CaveStep {
+    fn name(&self) -> &'static str {
+        return "Caves";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        for level in 0..MAP_DEPTH {
+            for y in 0..(MAP_SIZE.1) {
+                for x in 0..(MAP_SIZE.0) {
+                    let pos = (x, y, level);
+
+                    let state = if context.sample(pos, GENERATION_CAVE_SIZE, (0.0, 0.0)) <= GENERATION_CAVE_TRESHOLD {
+                        TileState::Solid
+                    } else {
+                        TileState::Empty
+                    };
+
+                    context.set_state(pos, state);
+                }
+            }
+        }
+    }
+}
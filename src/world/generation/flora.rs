@@ -0,0 +1,40 @@
+use crate::{globals::{MAP_SIZE, MAP_DEPTH}, world::tile::prelude::*};
+use super::{GenerationStep, GenerationContext, biome::Biome};
+
+// NOTE: Multiplier for the size of mushroom patches.
+pub const MUSHROOM_PATCH_SIZE: f64 = 12.0;
+
+// NOTE: Offset that is applied to the noise, when placing mushrooms.
+pub const MUSHROOM_NOISE_OFFSET: (f64, f64) = (80.0, 80.0);
+
+// NOTE: Treshold the noise has to be over for a mushroom to grow.
+pub const MUSHROOM_TRESHOLD: f64 = 0.05;
+
+// NOTE: Grows mushroom forests on the empty tiles of fungal biomes.
+pub struct MushroomStep;
+
+impl GenerationStep for MushroomStep {
+    fn name(&self) -> &'static str {
+        return "Mushrooms";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        for level in 0..MAP_DEPTH {
+            for y in 0..(MAP_SIZE.1) {
+                for x in 0..(MAP_SIZE.0) {
+                    let pos = (x, y, level);
+
+                    if context.biome(pos) != Biome::Fungal
+                        || context.state(pos) != TileState::Empty
+                        || context.is_reserved(pos) {
+                        continue;
+                    }
+
+                    if context.sample(pos, MUSHROOM_PATCH_SIZE, MUSHROOM_NOISE_OFFSET) > MUSHROOM_TRESHOLD {
+                        context.set_state(pos, TileState::Mushroom);
+                    }
+                }
+            }
+        }
+    }
+}
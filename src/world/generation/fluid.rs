@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+use rand::Rng;
+
+use crate::{globals::{MAP_SIZE, MAP_DEPTH}, world::tile::prelude::*};
+use super::{GenerationStep, GenerationContext, generation_area_count};
+
+// NOTE: Number of underground lakes on every level of a generation area.
+pub const LAKE_COUNT: std::ops::Range<usize> = 1..4;
+
+// NOTE: Range of the number of tiles a lake covers.
+pub const LAKE_SIZE: std::ops::Range<usize> = 8..40;
+
+// NOTE: Number of lava rivers on the second level of a generation
+//       area, every deeper level has one more. The surface has none.
+pub const LAVA_RIVER_COUNT: usize = 1;
+
+// NOTE: Range of the number of steps a lava river takes.
+pub const LAVA_RIVER_LENGTH: std::ops::Range<usize> = 20..60;
+
+// NOTE: Maximum change of the direction of a lava river on each step, in radians.
+pub const LAVA_RIVER_WANDER: f32 = 0.6;
+
+// NOTE: Fills parts of the caves with water. Lakes start from a random
+//       empty tile and flood the empty tiles around it.
+pub struct LakeStep;
+
+impl GenerationStep for LakeStep {
+    fn name(&self) -> &'static str {
+        return "Lakes";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        for level in 0..MAP_DEPTH {
+            let count = context.rng.gen_range(LAKE_COUNT) * generation_area_count();
+
+            for _ in 0..count {
+                let start = (
+                    context.rng.gen_range(0..MAP_SIZE.0),
+                    context.rng.gen_range(0..MAP_SIZE.1),
+                    level,
+                );
+
+                if context.state(start) != TileState::Empty || context.is_reserved(start) {
+                    continue;
+                }
+
+                let size = context.rng.gen_range(LAKE_SIZE);
+
+                let mut queue = VecDeque::from([start]);
+                let mut filled = 0;
+
+                while let Some(pos) = queue.pop_front() {
+                    if filled >= size {
+                        break;
+                    }
+
+                    if context.state(pos) != TileState::Empty || context.is_reserved(pos) {
+                        continue;
+                    }
+
+                    context.set_state(pos, TileState::Water);
+                    filled += 1;
+
+                    for (x, y) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                        let next = (pos.0 as i32 + x, pos.1 as i32 + y);
+
+                        if context.in_bounds(next) {
+                            queue.push_back((next.0 as usize, next.1 as usize, level));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// NOTE: Adds rivers of lava to the levels below the surface. Rivers start
+//       from a random tile and wander around, melting everything on the way.
+pub struct LavaStep;
+
+impl GenerationStep for LavaStep {
+    fn name(&self) -> &'static str {
+        return "Lava";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        for level in 1..MAP_DEPTH {
+            let count = (LAVA_RIVER_COUNT + level - 1) * generation_area_count();
+
+            for _ in 0..count {
+                let mut pos = (
+                    context.rng.gen_range(0..MAP_SIZE.0) as f32,
+                    context.rng.gen_range(0..MAP_SIZE.1) as f32,
+                );
+
+                let mut angle: f32 = context.rng.gen_range(0.0..std::f32::consts::TAU);
+                let length = context.rng.gen_range(LAVA_RIVER_LENGTH);
+
+                for _ in 0..length {
+                    let tile = (pos.0.round() as i32, pos.1.round() as i32);
+
+                    if !context.in_bounds(tile) {
+                        break;
+                    }
+
+                    let tile = (tile.0 as usize, tile.1 as usize, level);
+
+                    // NOTE: Passages and ores are never melted.
+                    if !context.is_reserved(tile) {
+                        context.set_state(tile, TileState::Lava);
+                    }
+
+                    angle += context.rng.gen_range(-LAVA_RIVER_WANDER..=LAVA_RIVER_WANDER);
+
+                    pos.0 += angle.cos();
+                    pos.1 += angle.sin();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use log::info;
+use rand::Rng;
+
+use crate::{globals::{MAP_SIZE, MAP_DEPTH}, world::tile::prelude::*};
+use super::{GenerationStep, GenerationContext, generation_area_count};
+
+// NOTE: Minimum distance that two seperate resources could be.
+pub const MINIMUM_RESOURCE_DISTANCE: f32 = 8.0;
+
+// NOTE: How much distance reduced on each maximum attempt.
+pub const RESOURCE_DISTANCE_REDUCTION: f32 = 0.5;
+
+// NOTE: Maximum tries for a resource location
+pub const MAXIMUM_RESOURCE_ITERATION: usize = 15;
+
+// NOTE: Decides whether or not resources can spread to empty tile.
+pub const CAN_SPREAD_TO_FREE_TILE: bool = true;
+
+// NOTE: Places the ore veins of every level. Veins are only accepted in
+//       the biomes that favour their material, and deeper levels have
+//       more veins of rare materials.
+pub struct OreStep;
+
+impl GenerationStep for OreStep {
+    fn name(&self) -> &'static str {
+        return "Ores";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        const RESOURCE_COUNT: usize = 4;
+
+        let materials: [ResourceMaterial; RESOURCE_COUNT] = [
+            ResourceMaterial::Coal,
+            ResourceMaterial::Iron,
+            ResourceMaterial::Gold,
+            ResourceMaterial::Crystal,
+        ];
+
+        for level in 0..MAP_DEPTH {
+            let resources = materials.map(|m| (
+                (context.rng.gen_range(m.range()) + m.depth_bonus() * level) * generation_area_count(),
+                m,
+            ));
+
+            for (count, material) in resources {
+                for _ in 0..count {
+                    let pos = pick_random_tile(context, level, material);
+
+                    context.reserve(pos);
+                    context.set_material(pos, material);
+                    context.veins.push((pos, material));
+
+                    spread_resource(context, pos, material, material.ratio());
+                }
+            }
+        }
+    }
+}
+
+#[allow(dead_code)]
+// NOTE: Calculates the score for a given tile. This will be
+//       used for future resoureces, currently unused.
+fn calculate_position_score(
+    pos: (usize, usize, usize),
+    context: &GenerationContext,
+) -> usize {
+    let mut score = 0;
+    let level = pos.2;
+
+    // NOTE: Calculate the score in x and y axis.
+    for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        let mut pos = (pos.0 as i32, pos.1 as i32);
+
+        while context.in_bounds(pos) && context.state((pos.0 as usize, pos.1 as usize, level)) == TileState::Solid {
+            pos.0 += dx;
+            pos.1 += dy;
+            score += 1;
+        }
+    }
+
+    return score;
+}
+
+// NOTE: Check distance between two resources, and returns a score. Only
+//       the resources within `MINIMUM_RESOURCE_DISTANCE` are checked, the
+//       score is `f32::MAX` if there are none.
+fn calculate_resource_score(
+    pos: (usize, usize, usize),
+    context: &GenerationContext,
+) -> f32 {
+    let mut small: f32 = f32::MAX;
+
+    let radius = MINIMUM_RESOURCE_DISTANCE.ceil() as i32;
+
+    for y in -radius..=radius {
+        for x in -radius..=radius {
+            let other = (pos.0 as i32 + x, pos.1 as i32 + y);
+
+            if !context.in_bounds(other) || !context.is_reserved((other.0 as usize, other.1 as usize, pos.2)) {
+                continue;
+            }
+
+            let distance: f32 = f32::sqrt((x.pow(2) + y.pow(2)) as f32);
+
+            if distance < small {
+                small = distance;
+            }
+        }
+    }
+
+    return small;
+}
+
+// NOTE: Picks a random solid position on the level for a vein of the
+//       material, the biome of the position has to accept the material.
+fn pick_random_tile(
+    context: &mut GenerationContext,
+    level: usize,
+    material: ResourceMaterial,
+) -> (usize, usize, usize) {
+    let mut iter = 0;
+    let mut distance = MINIMUM_RESOURCE_DISTANCE;
+
+    loop {
+        let pos = (
+            context.rng.gen_range(0..MAP_SIZE.0),
+            context.rng.gen_range(0..MAP_SIZE.1),
+            level,
+        );
+
+        let weight = context.biome(pos).ore_weight(material).clamp(0.0, 1.0);
+        let accepted = context.rng.gen_bool(weight);
+
+        if accepted
+            && context.state(pos) == TileState::Solid
+            && !context.is_reserved(pos)
+            && calculate_resource_score(pos, context) > distance {
+            return pos;
+        } else {
+            iter += 1;
+
+            if iter == MAXIMUM_RESOURCE_ITERATION {
+                info!(
+                    "Failed to find a suitable resource position
+                     during world generation with distance `{}`.",
+                    distance
+                );
+
+                distance -= RESOURCE_DISTANCE_REDUCTION;
+                iter = 0;
+            }
+        }
+    }
+}
+
+// NOTE: Speards resource to nearby tiles, creating more resources.
+//       Chance for a new resource to be created is reduced in every generation.
+fn spread_resource(
+    context: &mut GenerationContext,
+    pos: (usize, usize, usize),
+    material: ResourceMaterial,
+    ratio: f64,
+) {
+    if ratio < 0.0 {
+        return;
+    }
+
+    for y in -1..=1_i32 {
+        for x in -1..=1_i32 {
+            if x.abs() == y.abs() {
+                continue;
+            }
+
+            let spread = context.rng.gen_bool(ratio);
+
+            let next = (pos.0 as i32 + x, pos.1 as i32 + y);
+
+            if !context.in_bounds(next) {
+                continue;
+            }
+
+            let next = (next.0 as usize, next.1 as usize, pos.2);
+
+            let solid = context.state(next) == TileState::Solid;
+
+            if !spread || (!CAN_SPREAD_TO_FREE_TILE && !solid) || context.is_reserved(next) {
+                continue;
+            }
+
+            context.reserve(next);
+            context.set_material(next, material);
+
+            spread_resource(
+                context,
+                next,
+                material,
+                ratio - material.ratio_reduction_rate(),
+            );
+        }
+    }
+}
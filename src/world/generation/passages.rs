@@ -0,0 +1,70 @@
+use rand::Rng;
+
+use crate::{globals::{MAP_SIZE, MAP_DEPTH}, world::{tile::prelude::*, level::prelude::*}};
+use super::{GenerationStep, GenerationContext, generation_area_count};
+
+// NOTE: Number of passages between the surface and the level below it
+//       in every generation area, every deeper level has one less.
+pub const PASSAGE_COUNT: usize = 4;
+
+// NOTE: Maximum tries for a passage location that is empty on both
+//       levels, after that the passage is carved into the rock.
+pub const MAXIMUM_PASSAGE_ITERATION: usize = 50;
+
+// NOTE: Connects every level to the one below it with passages. Passages
+//       are placed on empty tiles of the upper level, and are carved into
+//       the lower level if there isn't an empty tile below them.
+pub struct PassageStep;
+
+impl GenerationStep for PassageStep {
+    fn name(&self) -> &'static str {
+        return "Passages";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        for level in 0..(MAP_DEPTH - 1) {
+            let count = PASSAGE_COUNT.saturating_sub(level).max(1) * generation_area_count();
+            let (upper, lower) = Passage::pair(level);
+
+            for _ in 0..count {
+                let mut iter = 0;
+
+                loop {
+                    iter += 1;
+
+                    let pos = (
+                        context.rng.gen_range(1..(MAP_SIZE.0 - 1)),
+                        context.rng.gen_range(1..(MAP_SIZE.1 - 1)),
+                    );
+
+                    let upper_pos = (pos.0, pos.1, level);
+                    let lower_pos = (pos.0, pos.1, level + 1);
+
+                    // NOTE: A tile can only hold a single passage.
+                    if context.is_reserved(upper_pos) || context.is_reserved(lower_pos) {
+                        continue;
+                    }
+
+                    let upper_empty = context.state(upper_pos) == TileState::Empty;
+                    let lower_empty = context.state(lower_pos) == TileState::Empty;
+
+                    // NOTE: Prefer the positions that are empty on both levels, settle
+                    //       for the ones that are only empty on the upper level, and
+                    //       finally carve both levels if nothing else is found.
+                    if (upper_empty && lower_empty)
+                        || (upper_empty && iter > MAXIMUM_PASSAGE_ITERATION)
+                        || iter > MAXIMUM_PASSAGE_ITERATION * 2 {
+                        for (pos, passage) in [(upper_pos, upper), (lower_pos, lower)] {
+                            context.set_state(pos, TileState::Empty);
+                            context.tile_mut(pos).passage = Some(passage);
+                            context.passages.insert(pos, passage);
+                            context.reserve(pos);
+                        }
+
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
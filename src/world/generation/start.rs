@@ -0,0 +1,236 @@
+use std::collections::{HashSet, VecDeque};
+use bevy::prelude::*;
+use log::info;
+
+use crate::{globals::{MAP_SIZE, MAP_DEPTH}, world::tile::prelude::*};
+use super::{GenerationStep, GenerationContext};
+
+// NOTE: Position the starting cave should be as close as possible to,
+//       the camera starts from the bottom left corner of the map.
+pub const START_AREA_ORIGIN: (usize, usize) = (50, 35);
+
+// NOTE: Minimum number of walkable tiles a cave needs to be the starting cave.
+pub const MINIMUM_START_CAVE_SIZE: usize = 60;
+
+// NOTE: Half of the size of the room that is carved at the origin, if
+//       none of the caves are large enough to be the starting cave.
+pub const START_ROOM_RADIUS: usize = 5;
+
+// NOTE: Ore veins within this distance to the start are connected to the
+//       starting cave with tunnels, if they aren't already reachable.
+pub const ORE_TUNNEL_RADIUS: usize = 40;
+
+// NOTE: Resource that holds the starting cave of the world, pawns of the
+//       player are placed on its free tiles. Tiles are sorted by their
+//       distance to the center.
+#[derive(Resource)]
+pub struct StartArea {
+    pub center: (usize, usize, usize),
+    pub tiles: Vec<(usize, usize, usize)>,
+}
+
+// NOTE: Returns every walkable tile that can be reached from the given
+//       position without leaving its level, in the order they are found.
+pub fn flood_fill(
+    context: &GenerationContext,
+    start: (usize, usize, usize),
+) -> Vec<(usize, usize, usize)> {
+    let mut visited: HashSet<(usize, usize, usize)> = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    let mut region = vec![];
+
+    while let Some(pos) = queue.pop_front() {
+        region.push(pos);
+
+        for next in neighbours(context, pos) {
+            if context.walkable(next) && visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    return region;
+}
+
+// NOTE: Returns the orthogonal neighbours of a position that are in bounds.
+fn neighbours(
+    context: &GenerationContext,
+    pos: (usize, usize, usize),
+) -> Vec<(usize, usize, usize)> {
+    let mut result = Vec::with_capacity(4);
+
+    for (x, y) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+        let next = (pos.0 as i32 + x, pos.1 as i32 + y);
+
+        if context.in_bounds(next) {
+            result.push((next.0 as usize, next.1 as usize, pos.2));
+        }
+    }
+
+    return result;
+}
+
+fn origin_distance(pos: (usize, usize, usize)) -> usize {
+    return pos.0.abs_diff(START_AREA_ORIGIN.0).pow(2) + pos.1.abs_diff(START_AREA_ORIGIN.1).pow(2);
+}
+
+// NOTE: Splits the walkable tiles of every level into caves, and picks the
+//       cave on the surface that is large enough and closest to the origin
+//       as the starting cave. A room is carved at the origin if none is.
+pub struct StartStep;
+
+impl GenerationStep for StartStep {
+    fn name(&self) -> &'static str {
+        return "Start";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        for level in 0..MAP_DEPTH {
+            // NOTE: Label of the cave every tile belongs to, 0 is unlabeled.
+            let mut labels = vec![0_u32; MAP_SIZE.0 * MAP_SIZE.1];
+            let mut caves = 0;
+
+            for y in 0..(MAP_SIZE.1) {
+                for x in 0..(MAP_SIZE.0) {
+                    let pos = (x, y, level);
+
+                    if labels[y * MAP_SIZE.0 + x] != 0 || !context.walkable(pos) {
+                        continue;
+                    }
+
+                    caves += 1;
+
+                    let mut queue = VecDeque::from([pos]);
+                    let mut size = 0;
+                    let mut closest = pos;
+
+                    labels[y * MAP_SIZE.0 + x] = caves;
+
+                    while let Some(pos) = queue.pop_front() {
+                        size += 1;
+
+                        if origin_distance(pos) < origin_distance(closest) {
+                            closest = pos;
+                        }
+
+                        for next in neighbours(context, pos) {
+                            let label = &mut labels[next.1 * MAP_SIZE.0 + next.0];
+
+                            if *label == 0 && context.walkable(next) {
+                                *label = caves;
+                                queue.push_back(next);
+                            }
+                        }
+                    }
+
+                    let better = match context.start {
+                        Some(start) => origin_distance(closest) < origin_distance(start),
+                        None => true,
+                    };
+
+                    if level == 0 && size >= MINIMUM_START_CAVE_SIZE && better {
+                        context.start = Some(closest);
+                    }
+                }
+            }
+
+            context.caves[level] = caves as usize;
+        }
+
+        if context.start.is_some() {
+            return;
+        }
+
+        info!(
+            "Failed to find a cave with at least `{}` tiles, carving the starting cave.",
+            MINIMUM_START_CAVE_SIZE,
+        );
+
+        for y in (START_AREA_ORIGIN.1 - START_ROOM_RADIUS)..=(START_AREA_ORIGIN.1 + START_ROOM_RADIUS) {
+            for x in (START_AREA_ORIGIN.0 - START_ROOM_RADIUS)..=(START_AREA_ORIGIN.0 + START_ROOM_RADIUS) {
+                let pos = (x, y, 0);
+
+                if !context.is_reserved(pos) {
+                    context.set_state(pos, TileState::Empty);
+                }
+            }
+        }
+
+        context.caves[0] += 1;
+        context.start = Some((START_AREA_ORIGIN.0, START_AREA_ORIGIN.1, 0));
+    }
+}
+
+// NOTE: Carves tunnels from the ore veins around the start to the starting
+//       cave, so every vein close to the start can be reached and mined.
+pub struct TunnelStep;
+
+impl GenerationStep for TunnelStep {
+    fn name(&self) -> &'static str {
+        return "Tunnels";
+    }
+
+    fn run(&self, context: &mut GenerationContext) {
+        let start = match context.start {
+            Some(s) => s,
+            None => {
+                error!("Tunnels can't be carved before the starting cave is picked.");
+                panic!();
+            }
+        };
+
+        let mut region: HashSet<(usize, usize, usize)> = flood_fill(context, start)
+            .into_iter()
+            .collect();
+
+        let veins: Vec<(usize, usize, usize)> = context.veins.iter()
+            .map(|(pos, _)| *pos)
+            .filter(|pos| {
+                pos.2 == start.2
+                    && pos.0.abs_diff(start.0) <= ORE_TUNNEL_RADIUS
+                    && pos.1.abs_diff(start.1) <= ORE_TUNNEL_RADIUS
+            })
+            .collect();
+
+        let mut tunnels = 0;
+
+        for vein in veins {
+            // NOTE: Vein is already reachable from the starting cave.
+            if neighbours(context, vein).iter().any(|n| region.contains(&n)) {
+                continue;
+            }
+
+            tunnels += 1;
+
+            // NOTE: Walk from the vein towards the start, on the axis with the
+            //       larger distance left, until the starting cave is reached.
+            //       Passages and ores on the way are left intact.
+            let mut pos = vein;
+            let mut tunnel = vec![];
+
+            loop {
+                let dx = start.0 as i32 - pos.0 as i32;
+                let dy = start.1 as i32 - pos.1 as i32;
+
+                pos = if dx.abs() >= dy.abs() {
+                    ((pos.0 as i32 + dx.signum()) as usize, pos.1, pos.2)
+                } else {
+                    (pos.0, (pos.1 as i32 + dy.signum()) as usize, pos.2)
+                };
+
+                if region.contains(&pos) {
+                    break;
+                }
+
+                if !context.is_reserved(pos) {
+                    context.set_state(pos, TileState::Empty);
+                    tunnel.push(pos);
+                }
+            }
+
+            region.extend(tunnel);
+        }
+
+        info!("Carved `{}` tunnels to the ores around the start.", tunnels);
+    }
+}
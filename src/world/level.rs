@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use log::info;
+use serde::{Serialize, Deserialize};
+
+use crate::{pawn::prelude::*, globals};
+
+pub mod prelude {
+    pub use super::{
+        Passage,
+        ViewedLevel,
+    };
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ViewedLevel>()
+            .add_system_to_stage(CoreStage::PreUpdate, switch_viewed_level)
+            .add_system_to_stage(CoreStage::PostUpdate, update_level_visibility);
+    }
+}
+
+// NOTE: Passages connect a tile to the tile with the same position on the
+//       level above or below, every passage has a matching one on the other
+//       end. Ramps only connect the surface to the first level below it,
+//       deeper levels are only connected by stairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Passage {
+    StairsUp,
+    StairsDown,
+    RampUp,
+    RampDown,
+}
+
+impl Passage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Passage::StairsUp => "Stairs Up",
+            Passage::StairsDown => "Stairs Down",
+            Passage::RampUp => "Ramp Up",
+            Passage::RampDown => "Ramp Down",
+        }
+    }
+
+    pub fn glyph(&self) -> usize {
+        return match self {
+            Passage::StairsUp => '<',
+            Passage::StairsDown => '>',
+            Passage::RampUp => 30 as char,
+            Passage::RampDown => 31 as char,
+        } as usize;
+    }
+
+    // NOTE: Returns the change in level when the passage is taken.
+    pub fn offset(&self) -> i32 {
+        match self {
+            Passage::StairsUp | Passage::RampUp => -1,
+            Passage::StairsDown | Passage::RampDown => 1,
+        }
+    }
+
+    // NOTE: Returns the pair of passages that connect the given level
+    //       to the one below it, the first one is on the upper level.
+    pub fn pair(level: usize) -> (Passage, Passage) {
+        if level == 0 {
+            return (Passage::RampDown, Passage::RampUp);
+        }
+
+        return (Passage::StairsDown, Passage::StairsUp);
+    }
+
+    // NOTE: Extra movement cost of taking the passage, stairs are slower.
+    pub fn cost(&self) -> u32 {
+        match self {
+            Passage::StairsUp | Passage::StairsDown => 3,
+            Passage::RampUp | Passage::RampDown => 1,
+        }
+    }
+}
+
+// NOTE: Resource that holds the level the player is looking at, only the
+//       tiles and pawns on this level are visible, and every order the
+//       player gives with the cursor targets this level.
+#[derive(Resource, Debug, Default)]
+pub struct ViewedLevel {
+    pub level: usize,
+}
+
+// NOTE: Comma and period move the view one level up or down.
+fn switch_viewed_level(
+    mut viewed: ResMut<ViewedLevel>,
+    keys: Res<Input<KeyCode>>,
+) {
+    let level = viewed.level;
+
+    if keys.just_pressed(KeyCode::Comma) && level > 0 {
+        viewed.level -= 1;
+    }
+
+    if keys.just_pressed(KeyCode::Period) && level + 1 < globals::MAP_DEPTH {
+        viewed.level += 1;
+    }
+
+    if viewed.level != level {
+        info!("Viewing level `{}`.", viewed.level);
+    }
+}
+
+// NOTE: Hides everything that has a position on a level other than the
+//       viewed one. Every entity is updated when the viewed level changes,
+//       otherwise only the entities that changed their position are.
+fn update_level_visibility(
+    mut queries: ParamSet<(
+        Query<(&Position, &mut Visibility)>,
+        Query<(&Position, &mut Visibility), Changed<Position>>,
+    )>,
+    viewed: Res<ViewedLevel>,
+) {
+    let level = viewed.level as i32;
+
+    if viewed.is_changed() {
+        for (position, mut visibility) in &mut queries.p0() {
+            visibility.is_visible = position.z == level;
+        }
+    } else {
+        for (position, mut visibility) in &mut queries.p1() {
+            visibility.is_visible = position.z == level;
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::{prelude::*, render::mesh::{Indices, PrimitiveTopology}, sprite::{MaterialMesh2dBundle, Mesh2dHandle}};
+
+use crate::{globals, tileset};
+use super::chunk::{self, TileChunk, CHUNK_SIZE};
+
+pub struct RenderPlugin;
+
+impl Plugin for RenderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::PreStartup, setup_tile_material)
+            .add_system_to_stage(CoreStage::PostUpdate, rebuild_dirty_chunks);
+    }
+}
+
+// NOTE: Resource that holds the material every chunk mesh is drawn with,
+//       tile colors are stored in the vertices and tinted on the tileset.
+#[derive(Resource)]
+pub struct TileMaterial(Handle<ColorMaterial>);
+
+// NOTE: Creates the `TileMaterial` resource from the tileset image.
+fn setup_tile_material(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(ColorMaterial {
+        color: Color::WHITE,
+        texture: Some(assets.load(tileset::TILESET_PATH)),
+    });
+
+    commands.insert_resource(TileMaterial(material));
+}
+
+// NOTE: Builds the mesh of a chunk, every tile in the chunk is a quad
+//       with the glyph and the color of its `TileData`. Vertices are in
+//       world coordinates, chunk position is in chunks.
+pub fn build_chunk_mesh(chunk: &chunk::Chunk, position: (usize, usize)) -> Mesh {
+    let capacity = CHUNK_SIZE * CHUNK_SIZE;
+
+    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(capacity * 4);
+    let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(capacity * 4);
+    let mut colors: Vec<[f32; 4]> = Vec::with_capacity(capacity * 4);
+    let mut indices: Vec<u32> = Vec::with_capacity(capacity * 6);
+
+    for (i, tile) in chunk.tiles.iter().enumerate() {
+        let x = position.0 * CHUNK_SIZE + i % CHUNK_SIZE;
+        let y = position.1 * CHUNK_SIZE + i / CHUNK_SIZE;
+
+        // NOTE: Chunks on the edges may be partially outside of the map.
+        if x >= globals::MAP_SIZE.0 || y >= globals::MAP_SIZE.1 {
+            continue;
+        }
+
+        let min = Vec2::new(x as f32, y as f32) * globals::SPRITE_SIZE;
+        let max = min + Vec2::splat(globals::SPRITE_SIZE);
+
+        let (uv_min, uv_max) = tileset::glyph_uv(tile.glyph());
+        // NOTE: Fluids are drawn over the tile, deeper fluids cover more of it.
+        let color = match tile.fluid {
+            Some(f) => f.overlay(tile.color()),
+            None => tile.color(),
+        }.as_linear_rgba_f32();
+
+        let start = positions.len() as u32;
+
+        // NOTE: Bottom left, bottom right, top right, top left. Texture
+        //       coordinates start from the top, so they are flipped.
+        positions.extend([
+            [min.x, min.y, 0.0],
+            [max.x, min.y, 0.0],
+            [max.x, max.y, 0.0],
+            [min.x, max.y, 0.0],
+        ]);
+
+        uvs.extend([
+            [uv_min.x, uv_max.y],
+            [uv_max.x, uv_max.y],
+            [uv_max.x, uv_min.y],
+            [uv_min.x, uv_min.y],
+        ]);
+
+        colors.extend([color; 4]);
+
+        indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.set_indices(Some(Indices::U32(indices)));
+
+    return mesh;
+}
+
+// NOTE: Spawns the entity that draws a chunk mesh on the world layer.
+pub fn spawn_chunk_mesh(
+    commands: &mut Commands,
+    mesh: Handle<Mesh>,
+    material: &TileMaterial,
+) -> Entity {
+    return commands
+        .spawn(MaterialMesh2dBundle {
+            mesh: Mesh2dHandle(mesh),
+            material: material.0.clone(),
+            transform: Transform::from_xyz(0.0, 0.0, globals::SPRITE_ORDER_WORLD),
+            ..Default::default()
+        })
+        .id();
+}
+
+// NOTE: Rebuilds the meshes of the loaded chunks that have changed since
+//       they were built, and clears the dirty flag of those chunks.
+fn rebuild_dirty_chunks(
+    mut world: ResMut<super::World>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<(&TileChunk, &Mesh2dHandle)>,
+) {
+    let width = chunk::chunk_count().0;
+
+    for (tile_chunk, handle) in &query {
+        let index = tile_chunk.position.1 * width + tile_chunk.position.0;
+
+        // NOTE: Check the flag without mutable access, so the
+        //       world isn't marked as changed every frame.
+        if !world.levels[tile_chunk.level].chunks[index].dirty {
+            continue;
+        }
+
+        let chunk = &mut world.levels[tile_chunk.level].chunks[index];
+        chunk.dirty = false;
+
+        if let Some(mesh) = meshes.get_mut(&handle.0) {
+            *mesh = build_chunk_mesh(chunk, tile_chunk.position);
+        }
+    }
+}
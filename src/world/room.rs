@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::pawn::prelude::*;
+
+pub mod prelude {
+    pub use super::{
+        RoomKind,
+        Rooms,
+    };
+}
+
+pub struct RoomPlugin;
+
+impl Plugin for RoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system_to_stage(StartupStage::Startup, setup_rooms);
+    }
+}
+
+// NOTE: Every kind of room a tile can be designated as.
+//       - Stockpile -> Pawns eat here.
+//       - Dormitory -> Pawns sleep here.
+//       - Portal    -> Recruited and summoned pawns arrive here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoomKind {
+    Stockpile,
+    Dormitory,
+    Portal,
+}
+
+impl RoomKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RoomKind::Stockpile => "Stockpile",
+            RoomKind::Dormitory => "Dormitory",
+            RoomKind::Portal => "Portal",
+        }
+    }
+
+    pub fn glyph(&self) -> usize {
+        match self {
+            RoomKind::Stockpile => 240,
+            RoomKind::Dormitory => 233,
+            RoomKind::Portal => 15,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            RoomKind::Stockpile => Color::rgba(0.2, 0.9, 0.2, 0.35),
+            RoomKind::Dormitory => Color::rgba(0.2, 0.5, 1.0, 0.35),
+            RoomKind::Portal => Color::rgba(0.7, 0.2, 1.0, 0.35),
+        }
+    }
+}
+
+// NOTE: Resource that holds every tile designated as a room.
+#[derive(Resource, Default)]
+pub struct Rooms {
+    pub tiles: Vec<(Position, RoomKind)>,
+}
+
+impl Rooms {
+    // NOTE: Returns the room kind of the given tile if it has one.
+    pub fn get(&self, position: &Position) -> Option<RoomKind> {
+        return self.tiles.iter()
+            .find(|(p, _)| p == position)
+            .map(|(_, k)| *k);
+    }
+
+    pub fn insert(&mut self, position: Position, kind: RoomKind) {
+        if self.get(&position).is_none() {
+            self.tiles.push((position, kind));
+        }
+    }
+
+    pub fn remove(&mut self, position: &Position) {
+        self.tiles.retain(|(p, _)| p != position);
+    }
+
+    // NOTE: Returns every tile of the given kind, sorted
+    //       from the closest to the farthest to `position`.
+    pub fn sorted_by_distance(&self, kind: RoomKind, position: &Position) -> Vec<Position> {
+        let mut tiles: Vec<Position> = self.tiles.iter()
+            .filter(|(_, k)| *k == kind)
+            .map(|(p, _)| *p)
+            .collect();
+
+        tiles.sort_by_key(|p| p.distance(position));
+
+        return tiles;
+    }
+}
+
+// NOTE: Sets up the `Rooms` resource.
+fn setup_rooms(mut commands: Commands) {
+    commands.insert_resource(Rooms::default());
+}
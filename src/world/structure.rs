@@ -0,0 +1,477 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+use log::info;
+
+use crate::{pawn::{prelude::*, core, worker}, util::prelude::*, turn_system};
+use super::tile::prelude::*;
+
+pub struct StructurePlugin;
+
+impl Plugin for StructurePlugin {
+    fn build(&self, app: &mut App) {
+        // NOTE: Traps trigger after the pawns moved, and before fluids and status
+        //       effects, they all roll on the same stream in this order.
+        app.init_resource::<TrapOccupants>()
+            .add_system_to_stage(CoreStage::Update, trigger_traps.after(core::process_pawn_turns));
+    }
+}
+
+// NOTE: Durability of a newly built door.
+pub const DOOR_DURABILITY: i32 = 20;
+
+// NOTE: Extra cost of pathing through a door that has to be broken down,
+//       in straight moves. Pawns prefer a longer way around if there is one.
+pub const DOOR_BREAK_COST: u32 = 10;
+
+// NOTE: Dice that are rolled for the damage a pawn deals to a door every
+//       turn it spends breaking it down.
+pub const DOOR_BREAK_DAMAGE: (u32, Die) = (1, Die::D6(0));
+
+// NOTE: Glyph that is used for the doors.
+pub const DOOR_GLYPH: usize = '+' as usize;
+
+// NOTE: Maximum distance in tiles at which turrets shoot at enemies.
+pub const TURRET_RANGE: u32 = 6;
+
+// NOTE: Every structure workers can build.
+//       - Pillar -> Holds the ceiling up, prevents cave-ins.
+//       - Door   -> Player pawns open it, enemies have to break it down.
+//       - Trap   -> Hurts the enemies that step on it or come in range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Structure {
+    #[default]
+    Pillar,
+    Door,
+    Trap(TrapKind),
+}
+
+impl Structure {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Structure::Pillar => "Pillar",
+            Structure::Door => "Door",
+            Structure::Trap(kind) => kind.name(),
+        }
+    }
+
+    pub fn glyph(&self) -> usize {
+        match self {
+            Structure::Pillar => TileState::Pillar.glyph(),
+            Structure::Door => DOOR_GLYPH,
+            Structure::Trap(kind) => kind.glyph(),
+        }
+    }
+
+    // NOTE: Material and quantity the structure costs, paid when the
+    //       structure is ordered and refunded if the order is cancelled.
+    pub fn cost(&self) -> (ResourceMaterial, usize) {
+        match self {
+            Structure::Pillar => (ResourceMaterial::Stone, 1),
+            Structure::Door => (ResourceMaterial::Stone, 2),
+            Structure::Trap(kind) => kind.cost(),
+        }
+    }
+}
+
+// NOTE: How a pawn can get through a door.
+//       - Open    -> Opens the door and walks through.
+//       - Break   -> Has to break the door down first.
+//       - Blocked -> Can't get through at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DoorAccess {
+    Open,
+    Break,
+    Blocked,
+}
+
+// NOTE: Door on a walkable tile, the durability works like the health
+//       of a pawn and the door is destroyed when it drops to zero.
+//       Locked doors block every pawn, regardless of their faction.
+#[derive(Debug, Clone, Copy)]
+pub struct Door {
+    pub durability: i32,
+    pub maximum: i32,
+    pub locked: bool,
+}
+
+impl Default for Door {
+    fn default() -> Self {
+        Self {
+            durability: DOOR_DURABILITY,
+            maximum: DOOR_DURABILITY,
+            locked: false,
+        }
+    }
+}
+
+impl Door {
+    // NOTE: Returns how a pawn gets through the door, by the relation of
+    //       its faction to the player who builds every door. Allies open
+    //       it, hostile pawns break it down and the rest can't get through.
+    pub fn access(&self, relation: Option<Relation>) -> DoorAccess {
+        if self.locked {
+            return DoorAccess::Blocked;
+        }
+
+        return match relation {
+            Some(Relation::Allied) => DoorAccess::Open,
+            Some(Relation::Hostile) => DoorAccess::Break,
+            _ => DoorAccess::Blocked,
+        };
+    }
+
+    pub fn color(&self) -> Color {
+        if self.locked {
+            return Color::hex("B03A2E").unwrap();
+        }
+
+        return Color::hex("8B5A2B").unwrap();
+    }
+
+    // NOTE: Damages the door, returns whether it is broken.
+    pub fn damage(&mut self, amount: i32) -> bool {
+        self.durability -= amount;
+
+        return self.durability <= 0;
+    }
+}
+
+// NOTE: Every kind of trap workers can build, traps only hurt the pawns
+//       of the factions that are hostile to the player.
+//       - Spike   -> Pierces the enemy that steps on it.
+//       - Fire    -> Burns the enemy that steps on it.
+//       - Boulder -> Crushes the enemy that steps on it, works only once.
+//       - Turret  -> Shoots the closest enemy that comes in its range and sight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrapKind {
+    Spike,
+    Fire,
+    Boulder,
+    Turret,
+}
+
+impl TrapKind {
+    pub const ALL: [TrapKind; 4] = [TrapKind::Spike, TrapKind::Fire, TrapKind::Boulder, TrapKind::Turret];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrapKind::Spike => "Spike Trap",
+            TrapKind::Fire => "Fire Trap",
+            TrapKind::Boulder => "Boulder Trap",
+            TrapKind::Turret => "Turret",
+        }
+    }
+
+    pub fn glyph(&self) -> usize {
+        match self {
+            TrapKind::Spike => '^' as usize,
+            TrapKind::Fire => '"' as usize,
+            TrapKind::Boulder => '*' as usize,
+            TrapKind::Turret => 'T' as usize,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            TrapKind::Spike => Color::hex("C0C0C0").unwrap(),
+            TrapKind::Fire => Color::hex("E8571B").unwrap(),
+            TrapKind::Boulder => Color::hex("8A7F70").unwrap(),
+            TrapKind::Turret => Color::hex("5A8FC0").unwrap(),
+        }
+    }
+
+    pub fn cost(&self) -> (ResourceMaterial, usize) {
+        match self {
+            TrapKind::Spike => (ResourceMaterial::Iron, 1),
+            TrapKind::Fire => (ResourceMaterial::Coal, 2),
+            TrapKind::Boulder => (ResourceMaterial::Stone, 4),
+            TrapKind::Turret => (ResourceMaterial::Iron, 3),
+        }
+    }
+
+    // NOTE: Maximum distance of the enemies the trap hits,
+    //       zero means only the enemy on the trap is hit.
+    pub fn range(&self) -> u32 {
+        match self {
+            TrapKind::Turret => TURRET_RANGE,
+            _ => 0,
+        }
+    }
+
+    // NOTE: Number of times the trap triggers before it has to be re-armed.
+    pub fn charges(&self) -> u32 {
+        match self {
+            TrapKind::Spike => 3,
+            TrapKind::Fire => 2,
+            TrapKind::Boulder => 1,
+            TrapKind::Turret => 8,
+        }
+    }
+
+    // NOTE: Dice and type of the damage that is dealt every time the trap triggers.
+    pub fn damage(&self) -> (u32, Die, DamageType) {
+        match self {
+            TrapKind::Spike => (2, Die::D6(0), DamageType::Piercing),
+            TrapKind::Fire => (2, Die::D6(0), DamageType::Fire),
+            TrapKind::Boulder => (4, Die::D6(0), DamageType::Bludgeoning),
+            TrapKind::Turret => (1, Die::D8(0), DamageType::Piercing),
+        }
+    }
+
+    // NOTE: Status effect that is applied to the enemy the trap hits.
+    pub fn status(&self) -> Option<StatusKind> {
+        match self {
+            TrapKind::Fire => Some(StatusKind::Burning),
+            _ => None,
+        }
+    }
+}
+
+// NOTE: Number of turns the status effects of the traps last.
+pub const TRAP_EFFECT_TURNS: u32 = 2;
+
+// NOTE: Trap on a walkable tile, every trigger uses a charge
+//       and traps without charges left don't trigger anymore.
+#[derive(Debug, Clone, Copy)]
+pub struct Trap {
+    pub kind: TrapKind,
+    pub charges: u32,
+}
+
+impl Trap {
+    pub fn new(kind: TrapKind) -> Self {
+        return Self {
+            kind,
+            charges: kind.charges(),
+        };
+    }
+
+    pub fn armed(&self) -> bool {
+        return self.charges > 0;
+    }
+
+    // NOTE: Traps that have to be re-armed are grayed out.
+    pub fn color(&self) -> Color {
+        if self.armed() {
+            return self.kind.color();
+        }
+
+        return Color::hex("505050").unwrap();
+    }
+}
+
+// NOTE: Resource that holds the enemies that were in the reach of every
+//       trap on the last turn, traps only trigger on the enemies that
+//       step on them or come in range, not on the ones that stay.
+#[derive(Resource, Debug, Default)]
+pub struct TrapOccupants(pub HashMap<(usize, usize, usize), Vec<Entity>>);
+
+// NOTE: Returns the enemies in the reach of the trap on the given position.
+//       Pressure traps reach the enemy standing on them, turrets the enemies
+//       they can see in their range.
+fn trap_reach(
+    world: &super::World,
+    trap: &Trap,
+    pos: (usize, usize, usize),
+    enemies: &[(Entity, Position)],
+) -> Vec<(Entity, Position)> {
+    let origin = Position::from(pos);
+    let range = trap.kind.range();
+
+    return enemies.iter()
+        .filter(|(_, p)| p.z == origin.z && p.distance(&origin) <= range.pow(2))
+        .filter(|(_, p)| range == 0 || world.has_line_of_sight(pos, (*p).into()))
+        .copied()
+        .collect();
+}
+
+// NOTE: Returns the enemy the trap on the given position hits, the closest
+//       one that wasn't in its reach on the last turn. Ties are broken by
+//       the entity.
+fn pick_trap_target(
+    pos: (usize, usize, usize),
+    reach: &[(Entity, Position)],
+    previous: &[Entity],
+) -> Option<Entity> {
+    let origin = Position::from(pos);
+
+    return reach.iter()
+        .filter(|(e, _)| !previous.contains(e))
+        .min_by_key(|(e, p)| (p.distance(&origin), *e))
+        .map(|(e, _)| *e);
+}
+
+// NOTE: Triggers the armed traps at the end of every turn, in the order of
+//       their positions. Traps that run out of charges are registered as
+//       build works, workers re-arm them without paying for them again.
+pub fn trigger_traps(
+    query: Query<(Entity, &Position, &Alignment), With<Pawn>>,
+    factions: Res<Factions>,
+    mut occupants: ResMut<TrapOccupants>,
+    mut world: ResMut<super::World>,
+    mut event_reader: EventReader<turn_system::TurnOverEvent>,
+    mut damage_ew: EventWriter<DamageEvent>,
+    mut status_ew: EventWriter<ApplyStatusEvent>,
+    mut register_work_ew: EventWriter<worker::RegisterGlobalWorkEvent>,
+    mut rng: ResMut<GameRng>,
+) {
+    let mut over = false;
+    for _ in event_reader.iter() {
+        over = true;
+    }
+
+    if !over {
+        return;
+    }
+
+    // NOTE: Forget the traps that were removed.
+    occupants.0.retain(|pos, _| world.traps.contains(pos));
+
+    if world.traps.is_empty() {
+        return;
+    }
+
+    let mut enemies: Vec<(Entity, Position)> = query.iter()
+        .filter(|(_, _, a)| factions.hostile(Alignment::Player, **a))
+        .map(|(e, p, _)| (e, *p))
+        .collect();
+    enemies.sort_by_key(|(e, _)| *e);
+
+    let mut traps: Vec<(usize, usize, usize)> = world.traps.iter().copied().collect();
+    traps.sort();
+
+    for pos in traps {
+        let trap = match world.get_tile(pos).trap {
+            Some(t) if t.armed() => t,
+            _ => continue,
+        };
+
+        let reach = trap_reach(&world, &trap, pos, &enemies);
+        let previous = occupants.0.insert(pos, reach.iter().map(|(e, _)| *e).collect());
+
+        let target = match pick_trap_target(pos, &reach, previous.as_deref().unwrap_or(&[])) {
+            Some(e) => e,
+            None => continue,
+        };
+
+        let (count, die, damage_type) = trap.kind.damage();
+
+        damage_ew.send(DamageEvent {
+            target,
+            amount: roll(count, die, 0, Advantage::Normal, rng.stream(RngStream::Combat)).total.max(1),
+            damage_type,
+            source: Some(Alignment::Player),
+        });
+
+        if let Some(kind) = trap.kind.status() {
+            status_ew.send(ApplyStatusEvent {
+                target,
+                kind,
+                turns: TRAP_EFFECT_TURNS,
+            });
+        }
+
+        let tile = world.get_tile_mut(pos);
+        let mut trap = trap;
+
+        trap.charges -= 1;
+        tile.trap = Some(trap);
+
+        if trap.armed() {
+            continue;
+        }
+
+        tile.marked = true;
+
+        info!("The {} at {:?} ran out of charges and has to be re-armed.", trap.kind.name(), pos);
+
+        let position = Position::from(pos);
+        let id = worker::GlobalWorkID::new(worker::BUILD_WORK_IDENTIFIER, &position);
+
+        register_work_ew.send(
+            worker::RegisterGlobalWorkEvent::new(
+                worker::GlobalWork::new(
+                    Task::Build(BuildTask::new(position, Structure::Trap(trap.kind), id.clone())),
+                    id,
+                    position,
+                ),
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn door_access() {
+        let mut door = Door::default();
+
+        assert_eq!(door.access(Some(Relation::Allied)), DoorAccess::Open);
+        assert_eq!(door.access(Some(Relation::Hostile)), DoorAccess::Break);
+        assert_eq!(door.access(Some(Relation::Neutral)), DoorAccess::Blocked);
+        assert_eq!(door.access(None), DoorAccess::Blocked);
+
+        door.locked = true;
+
+        for relation in [Some(Relation::Allied), Some(Relation::Hostile), Some(Relation::Neutral), None] {
+            assert_eq!(door.access(relation), DoorAccess::Blocked);
+        }
+    }
+
+    #[test]
+    fn door_access_by_alignment() {
+        let world = crate::world::test_world(crate::world::tile::TileState::Empty);
+        let door = Door::default();
+        let mut factions = Factions::default();
+
+        assert_eq!(world.door_access(&door, Some(&Alignment::Player), &factions), DoorAccess::Open);
+        assert_eq!(world.door_access(&door, Some(&Alignment::Enemy), &factions), DoorAccess::Break);
+        assert_eq!(world.door_access(&door, Some(&Alignment::Neutral), &factions), DoorAccess::Blocked);
+        assert_eq!(world.door_access(&door, None, &factions), DoorAccess::Blocked);
+
+        // NOTE: Doors follow the relations as soon as they change.
+        factions.set(Alignment::Player, Alignment::Neutral, Relation::Hostile);
+        assert_eq!(world.door_access(&door, Some(&Alignment::Neutral), &factions), DoorAccess::Break);
+    }
+
+    #[test]
+    fn trap_triggers_on_entry() {
+        let mut trap_world = crate::world::test_world(crate::world::tile::TileState::Empty);
+        trap_world.get_tile_mut((5, 5, 0)).trap = Some(Trap::new(TrapKind::Spike));
+        trap_world.traps.insert((5, 5, 0));
+
+        let mut world = World::new();
+        world.insert_resource(trap_world);
+        world.insert_resource(GameRng::new(0));
+        world.init_resource::<Factions>();
+        world.init_resource::<TrapOccupants>();
+        world.init_resource::<Events<turn_system::TurnOverEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ApplyStatusEvent>>();
+        world.init_resource::<Events<worker::RegisterGlobalWorkEvent>>();
+
+        let enemy = world.spawn((Pawn, Alignment::Enemy, Position::new(5, 5, 0))).id();
+
+        let mut system = IntoSystem::into_system(trigger_traps);
+        system.initialize(&mut world);
+
+        let mut reader = world.resource::<Events<DamageEvent>>().get_reader();
+        let mut hits = vec![];
+
+        // NOTE: The enemy stands on the trap for three turns, steps off and back on.
+        for (turn, position) in [(5, 5), (5, 5), (5, 5), (6, 5), (5, 5)].into_iter().enumerate() {
+            *world.get_mut::<Position>(enemy).unwrap() = Position::new(position.0, position.1, 0);
+            world.send_event(turn_system::TurnOverEvent { turn: turn as u64 + 1 });
+
+            system.run((), &mut world);
+
+            hits.push(reader.iter(world.resource::<Events<DamageEvent>>()).count());
+        }
+
+        assert_eq!(hits, vec![1, 0, 0, 0, 1]);
+        assert_eq!(world.resource::<crate::world::World>().get_tile((5, 5, 0)).trap.unwrap().charges, 1);
+    }
+}
@@ -0,0 +1,265 @@
+use bevy::prelude::*;
+use log::info;
+
+use crate::{pawn::{prelude::*, worker, combat}, util::prelude::*, globals::{MAP_SIZE, SPRITE_SIZE}};
+use super::tile::{self, TileState};
+
+pub struct SupportPlugin;
+
+impl Plugin for SupportPlugin {
+    fn build(&self, app: &mut App) {
+        // NOTE: Cave-ins roll on the same stream as the attacks, after them,
+        //       and their damage is applied on the same frame.
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            check_structural_support
+                .after(worker::mine_tile_event)
+                .after(combat::resolve_attacks)
+                .before(combat::apply_damage)
+        );
+    }
+}
+
+// NOTE: Maximum distance in tiles an open tile can be from the nearest wall
+//       or pillar, tiles further away than this collapse when they lose it.
+pub const MAXIMUM_UNSUPPORTED_SPAN: usize = 4;
+
+// NOTE: Dice that are rolled for the damage of the pawns caught in a cave-in.
+pub const CAVE_IN_DAMAGE: (u32, Die) = (2, Die::D6(0));
+
+// NOTE: Maximum distance in tiles a pawn caught in a cave-in is pushed out to.
+pub const CAVE_IN_ESCAPE_RANGE: i32 = MAXIMUM_UNSUPPORTED_SPAN as i32 * 2 + 1;
+
+// NOTE: Returns whether there is a wall or a pillar within the maximum
+//       span of the position, the edges of the map always hold the ceiling.
+fn is_supported(world: &super::World, pos: (usize, usize, usize)) -> bool {
+    let span = MAXIMUM_UNSUPPORTED_SPAN as i32;
+
+    for y in -span..=span {
+        for x in -span..=span {
+            let other = (pos.0 as i32 + x, pos.1 as i32 + y);
+
+            if other.0 < 0 || other.1 < 0 || other.0 as usize >= MAP_SIZE.0 || other.1 as usize >= MAP_SIZE.1 {
+                return true;
+            }
+
+            if world.get_tile((other.0 as usize, other.1 as usize, pos.2)).state.supports() {
+                return true;
+            }
+        }
+    }
+
+    return false;
+}
+
+// NOTE: Returns the open tiles around a mined tile that were only held up by
+//       it. Tiles that were unsupported before the tile was mined are never
+//       part of them, so the natural caves don't collapse on their own.
+pub fn unsupported_tiles(world: &super::World, mined: (usize, usize, usize)) -> Vec<(usize, usize, usize)> {
+    let span = MAXIMUM_UNSUPPORTED_SPAN as i32;
+    let mut result = vec![];
+
+    for y in -span..=span {
+        for x in -span..=span {
+            let pos = (mined.0 as i32 + x, mined.1 as i32 + y);
+
+            if pos.0 < 0 || pos.1 < 0 || pos.0 as usize >= MAP_SIZE.0 || pos.1 as usize >= MAP_SIZE.1 {
+                continue;
+            }
+
+            let pos = (pos.0 as usize, pos.1 as usize, mined.2);
+            let tile = world.get_tile(pos);
+
+            // NOTE: Passages are built to last.
+            if tile.state.supports() || tile.passage.is_some() {
+                continue;
+            }
+
+            if !is_supported(world, pos) {
+                result.push(pos);
+            }
+        }
+    }
+
+    return result;
+}
+
+// NOTE: Returns the closest free tile a pawn caught in a cave-in on the
+//       position can be pushed out to, ties are broken by the position.
+fn find_escape_tile(world: &super::World, pos: (usize, usize, usize)) -> Option<(usize, usize, usize)> {
+    let origin = Position::from(pos);
+    let mut result = None;
+
+    for y in -CAVE_IN_ESCAPE_RANGE..=CAVE_IN_ESCAPE_RANGE {
+        for x in -CAVE_IN_ESCAPE_RANGE..=CAVE_IN_ESCAPE_RANGE {
+            let other = Position::new(origin.x + x, origin.y + y, origin.z);
+
+            if other.x < 0 || other.y < 0 {
+                continue;
+            }
+
+            let target = other.into();
+
+            if !world.is_walkable_tile(target) || world.get_tile(target).door.is_some() || world.get_entity(target).is_some() {
+                continue;
+            }
+
+            let key = (other.distance(&origin), other.x, other.y);
+
+            if result.map_or(true, |(k, _)| key < k) {
+                result = Some((key, target));
+            }
+        }
+    }
+
+    return result.map(|(_, t)| t);
+}
+
+// NOTE: Checks the structural integrity around every mined tile. Tiles that
+//       lost their support cave in and are filled with rubble. Pawns that
+//       are caught in a cave-in are damaged and pushed out to the closest
+//       free tile, if there is none the tile they stand on stays walkable
+//       but is covered with rubble.
+fn check_structural_support(
+    mut pawns: Query<(&mut Position, &mut Transform), With<Pawn>>,
+    mut world: ResMut<super::World>,
+    mut event_reader: EventReader<worker::MineTileEvent>,
+    mut damage_ew: EventWriter<DamageEvent>,
+    mut rng: ResMut<GameRng>,
+) {
+    for e in event_reader.iter() {
+        let tiles = unsupported_tiles(&world, e.0.into());
+
+        if tiles.is_empty() {
+            continue;
+        }
+
+        let mut buried = 0;
+        let mut trapped = vec![];
+
+        for pos in tiles {
+            let entity = world.get_entity(pos);
+
+            if let Some(entity) = entity {
+                let (count, die) = CAVE_IN_DAMAGE;
+
+                damage_ew.send(DamageEvent {
+                    target: entity,
+                    amount: roll(count, die, 0, Advantage::Normal, rng.stream(RngStream::Combat)).total.max(1),
+                    damage_type: DamageType::Bludgeoning,
+                    source: None,
+                });
+
+                trapped.push((entity, pos));
+            }
+
+            let tile = world.get_tile_mut(pos);
+
+            tile.state = TileState::Solid;
+            tile.resource = tile::Resource {
+                material: tile::ResourceMaterial::Stone,
+                quantity: tile::resource_yield(pos.2),
+            };
+            tile.fluid = None;
+            tile.door = None;
+            tile.trap = None;
+
+            world.traps.remove(&pos);
+
+            buried += 1;
+        }
+
+        // NOTE: Pawns are pushed out once every tile collapsed, so
+        //       they aren't pushed onto a tile that collapses later.
+        for (entity, pos) in trapped {
+            let target = match find_escape_tile(&world, pos) {
+                Some(t) => t,
+                None => {
+                    world.get_tile_mut(pos).state = TileState::Rubble;
+                    continue;
+                }
+            };
+
+            if let Ok((mut position, mut transform)) = pawns.get_mut(entity) {
+                world.set_entity(pos, None);
+                world.set_entity(target, Some(entity));
+
+                *position = target.into();
+                transform.translation.x = target.0 as f32 * SPRITE_SIZE;
+                transform.translation.y = target.1 as f32 * SPRITE_SIZE;
+            }
+        }
+
+        info!("Mining the tile at {:?} caused a cave-in, `{}` tiles collapsed.", e.0, buried);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::world;
+
+    // NOTE: Digs out a cave nine tiles tall, with pillars on its middle row.
+    fn cave(pillars: &[usize]) -> world::World {
+        let mut world = world::test_world(TileState::Solid);
+
+        for y in 100..=108 {
+            for x in 100..=130 {
+                world.get_tile_mut((x, y, 0)).state = TileState::Empty;
+            }
+        }
+
+        for x in pillars {
+            world.get_tile_mut((*x, 104, 0)).state = TileState::Pillar;
+        }
+
+        return world;
+    }
+
+    #[test]
+    fn collapse_within_span() {
+        let mut world = cave(&[104, 109, 114]);
+        world.get_tile_mut((109, 104, 0)).state = TileState::Empty;
+
+        // NOTE: The far end of the cave was never supported, and stays up.
+        assert!(!is_supported(&world, (125, 104, 0)));
+        assert_eq!(unsupported_tiles(&world, (109, 104, 0)), vec![(109, 104, 0)]);
+    }
+
+    #[test]
+    fn supported_cave() {
+        let mut world = cave(&[104, 108, 112]);
+        world.get_tile_mut((108, 104, 0)).state = TileState::Empty;
+
+        assert!(unsupported_tiles(&world, (108, 104, 0)).is_empty());
+    }
+
+    #[test]
+    fn pawn_pushed_out_of_cave_in() {
+        let mut cave = cave(&[104, 109, 114]);
+        cave.get_tile_mut((109, 104, 0)).state = TileState::Empty;
+
+        let mut world = World::new();
+        world.insert_resource(GameRng::new(0));
+        world.init_resource::<Events<worker::MineTileEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+
+        let pawn = world.spawn((Pawn, Position::new(109, 104, 0), Transform::default())).id();
+        cave.set_entity((109, 104, 0), Some(pawn));
+        world.insert_resource(cave);
+
+        world.send_event(worker::MineTileEvent(Position::new(109, 104, 0)));
+
+        let mut system = IntoSystem::into_system(check_structural_support);
+        system.initialize(&mut world);
+        system.run((), &mut world);
+
+        let cave = world.resource::<world::World>();
+
+        assert_eq!(cave.get_tile((109, 104, 0)).state, TileState::Solid);
+        assert_eq!(cave.get_entity((109, 104, 0)), None);
+        assert_eq!(cave.get_entity((108, 104, 0)), Some(pawn));
+        assert_eq!(*world.get::<Position>(pawn).unwrap(), Position::new(108, 104, 0));
+        assert_eq!(world.resource::<Events<DamageEvent>>().len(), 1);
+    }
+}
@@ -1,33 +1,37 @@
 use bevy::prelude::*;
 use bevy_egui::egui::Color32;
+use serde::Deserialize;
 
-use crate::pawn::prelude::*;
-use crate::{tileset, globals};
+use super::{level::Passage, fluid::Fluid, structure::{self, Door, Trap}};
 
 // TODO: Add necessary comments.
 
-pub struct TilePlugin;
-
-impl Plugin for TilePlugin {
-    fn build(&self, app: &mut App) {
-        app.add_system_to_stage(CoreStage::PreUpdate, update_tile_visuals);
-    }
-}
-
 pub mod prelude {
     pub use super::{
         TileState,
-        Tile,
         ResourceMaterial,
         Resource,
     };
 }
 
+// NOTE: Every kind of tile there is.
+//       - Empty    -> Walkable floor.
+//       - Solid    -> Wall that can be mined.
+//       - Water    -> Underground lake, blocks movement but not sight.
+//       - Lava     -> Lava river, blocks movement but not sight.
+//       - Mushroom -> Walkable floor covered with mushrooms.
+//       - Pillar   -> Built support that holds the ceiling up.
+//       - Rubble   -> Walkable floor covered with the rubble of a cave-in.
 #[allow(dead_code)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TileState {
     Empty,
     Solid,
+    Water,
+    Lava,
+    Mushroom,
+    Pillar,
+    Rubble,
 }
 
 impl TileState {
@@ -35,17 +39,45 @@ impl TileState {
         return match self {
             TileState::Empty => '.',
             TileState::Solid => '#',
+            TileState::Water | TileState::Lava => 247 as char,
+            TileState::Mushroom => 6 as char,
+            TileState::Pillar => 'O',
+            TileState::Rubble => ',',
         } as usize;
     }
-}
 
-#[derive(Component)]
-pub struct Tile {
-    pub state: TileState,
+    // NOTE: Color of the tile if it doesn't depend on its material.
+    pub fn color(&self) -> Option<Color> {
+        return match self {
+            TileState::Water => Some(Color::hex("2A5DB0").unwrap()),
+            TileState::Lava => Some(Color::hex("E8571B").unwrap()),
+            TileState::Mushroom => Some(Color::hex("3FB8A4").unwrap()),
+            TileState::Pillar => Some(Color::hex("9A9A9A").unwrap()),
+            TileState::Empty | TileState::Solid | TileState::Rubble => None,
+        };
+    }
+
+    pub fn walkable(&self) -> bool {
+        return matches!(self, TileState::Empty | TileState::Mushroom | TileState::Rubble);
+    }
+
+    // NOTE: Multiplier for the cost of walking into the tile.
+    pub fn movement_cost(&self) -> u32 {
+        return match self {
+            TileState::Mushroom => 2,
+            TileState::Rubble => 3,
+            _ => 1,
+        };
+    }
+
+    // NOTE: Returns whether the tile holds the ceiling above the tiles around it.
+    pub fn supports(&self) -> bool {
+        return matches!(self, TileState::Solid | TileState::Pillar);
+    }
 }
 
 #[allow(dead_code)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Deserialize)]
 pub enum ResourceMaterial {
     Dirt = 0,
     Stone,
@@ -111,6 +143,18 @@ impl ResourceMaterial {
         }
     }
 
+    // NOTE: Number of turns it takes an unskilled pawn to mine the material.
+    pub fn hardness(&self) -> u32 {
+        match self {
+            ResourceMaterial::Dirt => 2,
+            ResourceMaterial::Stone => 4,
+            ResourceMaterial::Coal => 3,
+            ResourceMaterial::Iron => 4,
+            ResourceMaterial::Gold => 4,
+            ResourceMaterial::Crystal => 5,
+        }
+    }
+
     pub fn range(&self) -> std::ops::Range<usize> {
         match self {
             ResourceMaterial::Dirt => 0..0,
@@ -121,6 +165,25 @@ impl ResourceMaterial {
             ResourceMaterial::Crystal => 3..(5 + 1),
         }
     }
+
+    // NOTE: Number of extra veins of the material for each level below
+    //       the surface, rare materials are more common on deeper levels.
+    pub fn depth_bonus(&self) -> usize {
+        match self {
+            ResourceMaterial::Dirt => 0,
+            ResourceMaterial::Stone => 0,
+            ResourceMaterial::Coal => 0,
+            ResourceMaterial::Iron => 1,
+            ResourceMaterial::Gold => 2,
+            ResourceMaterial::Crystal => 4,
+        }
+    }
+}
+
+// NOTE: Quantity of resources a tile yields when mined, for
+//       each level below the surface one extra is yielded.
+pub fn resource_yield(level: usize) -> usize {
+    return 1 + level;
 }
 
 #[derive(Component, Debug, Clone, Copy)]
@@ -134,6 +197,47 @@ pub struct TileData {
     pub state: TileState,
     pub resource: Resource,
     pub marked: bool,
+    pub passage: Option<Passage>,
+    pub fluid: Option<Fluid>,
+    pub door: Option<Door>,
+    pub trap: Option<Trap>,
+}
+
+impl TileData {
+    // NOTE: Passages, doors and traps are drawn over the state of the tile.
+    pub fn glyph(&self) -> usize {
+        if let Some(p) = self.passage {
+            return p.glyph();
+        }
+
+        if self.door.is_some() {
+            return structure::DOOR_GLYPH;
+        }
+
+        if let Some(trap) = self.trap {
+            return trap.kind.glyph();
+        }
+
+        return self.state.glyph();
+    }
+
+    pub fn color(&self) -> Color {
+        if let Some(door) = self.door {
+            return door.color();
+        }
+
+        if let Some(trap) = self.trap {
+            return trap.color();
+        }
+
+        return self.state.color().unwrap_or(self.resource.material.color());
+    }
+
+    // NOTE: Multiplier for the cost of walking into the tile, fluids
+    //       on the tile make it more expensive to walk through.
+    pub fn movement_cost(&self) -> u32 {
+        return self.state.movement_cost() + self.fluid.map_or(0, |f| f.movement_cost());
+    }
 }
 
 impl Default for TileData {
@@ -145,73 +249,10 @@ impl Default for TileData {
                 quantity: 0,
             },
             marked: false,
+            passage: None,
+            fluid: None,
+            door: None,
+            trap: None,
         }
     }
 }
-
-pub fn spawn_tile(
-    commands: &mut Commands,
-    tile_grid: &mut Vec<TileData>,
-    tileset: &tileset::Tileset,
-    position: (usize, usize),
-    state: TileState,
-    material: ResourceMaterial,
-) -> Entity {
-    // NOTE: Spwan and initialize the tile entity
-    let e = tileset::spawn_sprite_from_tileset(
-        commands, 
-        tileset, 
-        state.glyph(), 
-        Vec3::new(
-            position.0 as f32 * globals::SPRITE_SIZE,
-            position.1 as f32 * globals::SPRITE_SIZE,
-            globals::SPRITE_ORDER_WORLD,
-        ), 
-        Vec3::new(globals::SPRITE_SCALE, globals::SPRITE_SCALE, 1.0), 
-        material.color(),
-    );
-
-    commands.entity(e)
-        .insert(Position::from(position))
-        .insert(Tile {
-            state
-        })
-        .insert(Resource {
-            material,
-            quantity: 0,
-        });
-
-    // NOTE: Set the tile in the tile grid.
-    let tile = tile_grid.get_mut(
-        (position.1 * globals::MAP_SIZE.0) + position.0
-    ).unwrap();
-
-    *tile = TileData {
-        state,
-        resource: Resource {
-            material,
-            quantity: 1,
-        },
-        marked: false,
-    };
-    
-    return e;
-}
-
-fn update_tile_visuals(
-    mut query: Query<
-        (&Tile, &Resource, &mut TextureAtlasSprite), 
-        Or<(Changed<Tile>, Changed<Resource>)>,
-    >,
-) {
-    for (tile, res, mut sprite) in &mut query {
-        // NOTE: Change the glyph according to the `TileState`.
-        *sprite = TextureAtlasSprite::new(tile.state.glyph());
-
-        // NOTE: Change the color according to the `ResourceMaterial`.
-        sprite.color = res.material.color();
-
-        // NOTE: Set the anchor to defualt
-        sprite.anchor = globals::DEFAULT_SPRITE_ANCHOR;
-    }
-}
\ No newline at end of file